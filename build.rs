@@ -0,0 +1,20 @@
+//! Generates the mapping of const generic sizes to typenum numbers used by the dense module.
+use std::{env, fmt::Write, fs, path::Path};
+
+/// the largest size that can be used as a Network, see `dense::ToUInt`
+const MAX_SIZE: usize = 1024;
+
+fn main() {
+	let mut sizes = String::new();
+	for size in 0..=MAX_SIZE {
+		writeln!(
+			sizes,
+			"impl ToUInt for Const<{0}> {{ type Output = typenum::U{0}; }}",
+			size
+		)
+		.unwrap();
+	}
+	let out = env::var("OUT_DIR").unwrap();
+	fs::write(Path::new(&out).join("sizes.rs"), sizes).unwrap();
+	println!("cargo:rerun-if-changed=build.rs");
+}
@@ -1,6 +1,6 @@
 use generic_array::typenum;
 
-use dumbnet::prelude::*;
+use dumbnet::{prelude::*, schedule::Linear};
 
 /// ReLu does not really work with inputs outside of [0,1] even though it should in theory at least
 /// be able to handle much bigger inputs
@@ -9,7 +9,11 @@ fn main() {
 	let iter = (1..5).map(|i| ([i as f32 / 100.].into(), [(2 * i) as f32 / 100.].into()));
 	println!("untrained layer: {:?}", last);
 
-	last.teach(iter.clone(), 4000, |_, _| {});
+	let schedule = Linear {
+		start: 0.9,
+		end: 0.4,
+	};
+	last.teach(iter.clone(), 4000, 1, schedule, |_, _| {});
 
 	for (input, output) in iter {
 		let result = last.calculate(&input);
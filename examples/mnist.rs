@@ -0,0 +1,94 @@
+//! Learns to read handwritten digits.
+//!
+//! Download and unpack the four files from http://yann.lecun.com/exdb/mnist/ (or the same ones of
+//! Fashion-MNIST) into a directory, then run
+//!
+//!     cargo run --release --features std --example mnist -- path/to/directory
+use dumbnet::{
+	activation::Sigmoid,
+	datasets::Mnist,
+	initializer::GlorotUniform,
+	layers::{default_rng, InnerLayer, Network},
+	prelude::SoftMaxLayer,
+	schedule::Constant,
+	trainer::Trainer,
+};
+
+use generic_array::{
+	typenum::{U10, U32, U784},
+	GenericArray,
+};
+
+type Digits = InnerLayer<Sigmoid, U32, U784, U10, SoftMaxLayer<U10, U32>>;
+
+fn argmax(values: &GenericArray<f32, U10>) -> usize {
+	values
+		.iter()
+		.enumerate()
+		.fold((0, f32::NEG_INFINITY), |best, (i, &v)| {
+			if v > best.1 {
+				(i, v)
+			} else {
+				best
+			}
+		})
+		.0
+}
+
+fn accuracy(network: &Digits, data: &Mnist) -> f32 {
+	let correct = data
+		.iter()
+		.filter(|(image, label)| argmax(&network.calculate(image)) == argmax(label))
+		.count();
+	correct as f32 / data.len() as f32
+}
+
+fn main() {
+	let dir = std::env::args()
+		.nth(1)
+		.expect("pass the directory containing the mnist files");
+	let dir = std::path::Path::new(&dir);
+	let train = Mnist::open(
+		dir.join("train-images-idx3-ubyte"),
+		dir.join("train-labels-idx1-ubyte"),
+	)
+	.expect("could not read training data");
+	let test = Mnist::open(
+		dir.join("t10k-images-idx3-ubyte"),
+		dir.join("t10k-labels-idx1-ubyte"),
+	)
+	.expect("could not read test data");
+
+	let mut rng = default_rng();
+	let output = SoftMaxLayer::new_with_init::<GlorotUniform, _>(&mut rng);
+	let network = Digits::push_with_init::<GlorotUniform, _>(output, &mut rng);
+
+	let mut trainer = Trainer::new(network, Constant(0.5))
+		.with_epochs(20)
+		.with_batch_size(10)
+		.with_patience(2);
+	// the last sixth of the training images decides when to stop, the test images stay unseen
+	let validation = train.len() / 6;
+	let training = train.len() - validation;
+	println!(
+		"{} training, {} validation and {} test images",
+		training,
+		validation,
+		test.len()
+	);
+	let report = trainer.fit(
+		train.iter().take(training),
+		train.iter().skip(training),
+		|epoch, _| {
+			println!(
+				"epoch {}: training loss {}, validation loss {}",
+				epoch.epoch, epoch.training_loss, epoch.validation_loss
+			);
+		},
+	);
+	println!("best epoch was {}", report.best_epoch);
+	println!(
+		"{:.1}% of the test images were read correctly",
+		100. * accuracy(trainer.network(), &test)
+	);
+}
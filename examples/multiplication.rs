@@ -1,4 +1,7 @@
-use generic_array::{typenum, GenericArray};
+use generic_array::{
+	typenum::{U1, U2, U4},
+	GenericArray,
+};
 use itertools::Itertools;
 
 use std::{
@@ -6,13 +9,9 @@ use std::{
 	io::{BufRead, BufReader, BufWriter, Write},
 };
 
-use bincode;
-use gnuplot;
+use dumbnet::{activation::Sigmoid, layers::Network, network, schedule::Linear, trainer::Trainer};
 
-use dumbnet::{
-	activation::Sigmoid,
-	layers::{InnerLayer, Layer, OutputLayer},
-};
+network!(type Multiplier = in U2 => Dense<Sigmoid, U4> => Dense<Sigmoid, U4> => Dense<Sigmoid, U1>);
 
 fn read_gnuplot<R: BufRead>(rdr: &mut R) -> (Vec<f32>, Vec<f32>) {
 	rdr.lines()
@@ -26,10 +25,8 @@ fn read_gnuplot<R: BufRead>(rdr: &mut R) -> (Vec<f32>, Vec<f32>) {
 		.unzip()
 }
 pub fn main() {
-	let bottom = OutputLayer::<Sigmoid, typenum::U1, _>::new();
-	let hidden_layer = InnerLayer::<Sigmoid, typenum::U4, _, _, _, _, _>::push(bottom);
-	let mut input_layer =
-		InnerLayer::<Sigmoid, typenum::U4, typenum::U2, _, _, _, _>::push(hidden_layer);
+	let mut input_layer: Multiplier =
+		network![in U2 => Dense<Sigmoid, U4> => Dense<Sigmoid, U4> => Dense<Sigmoid, U1>];
 
 	/*
 	let first = arr![u8; 2,3,4,5];
@@ -43,7 +40,7 @@ pub fn main() {
 	// transform integers into -1..1 for the network
 	let iter = first
 		.into_iter()
-		.cartesian_product(second.into_iter())
+		.cartesian_product(second)
 		.map(|(fst, snd)| {
 			let input: GenericArray<f32, _> = [f32::from(fst) / 100., f32::from(snd) / 100.].into();
 			let output = f32::from(fst * snd) / 100.;
@@ -67,30 +64,52 @@ pub fn main() {
 			remove_file("multiply_network").unwrap();
 		},
 		"train" => {
-			let iterations = args
+			let epochs = args
 				.next()
-				.map(|s| s.parse().ok())
-				.flatten()
+				.and_then(|s| s.parse().ok())
 				.unwrap_or(2000usize);
-			println!("training for {} iterations", iterations);
+			println!("training for up to {} epochs", epochs);
 
 			let mut data_file = BufWriter::new(File::create("multiply_data").unwrap());
 
-			let progress = indicatif::ProgressBar::new(iterations as u64);
+			let progress = indicatif::ProgressBar::new(epochs as u64);
 			progress.set_message("training");
 
-			input_layer.teach(iter.clone(), iterations, |iter, loss| {
-				if iter % ((iterations / 100) + 1) == 0 {
-					progress.set_position(iter as u64);
+			// check how well it generalizes on the swapped inputs
+			let validation = iter.clone().map(|(mut input, output)| {
+				input.swap(0, 1);
+				(input, output)
+			});
+
+			// start quick, slow down over time
+			let schedule = Linear {
+				start: 0.9,
+				end: 0.4,
+			};
+			let mut trainer = Trainer::new(input_layer, schedule)
+				.with_epochs(epochs)
+				.with_patience(200);
+			let report = trainer.fit(iter.clone(), validation, |epoch, network| {
+				if epoch.epoch % ((epochs / 100) + 1) == 0 {
+					progress.set_position(epoch.epoch as u64);
 					data_file
-						.write_all(format!("{} {}\n", iter, loss).as_bytes())
+						.write_all(
+							format!("{} {}\n", epoch.epoch, epoch.validation_loss).as_bytes(),
+						)
 						.unwrap()
 				}
+				if epoch.improved {
+					let mut net_file = File::create("multiply_network").unwrap();
+					bincode::serialize_into(&mut net_file, network).unwrap();
+				}
 			});
 
 			progress.finish();
-			let mut net_file = File::create("multiply_network").unwrap();
-			bincode::serialize_into(&mut net_file, &input_layer).unwrap();
+			println!(
+				"best validation loss {} after {} epochs",
+				report.best_validation_loss,
+				report.best_epoch + 1
+			);
 		},
 
 		_ => {
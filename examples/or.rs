@@ -1,6 +1,7 @@
 use dumbnet::{
 	activation::Sigmoid,
-	layers::{Layer, OutputLayer},
+	layers::{Network, OutputLayer},
+	schedule::Linear,
 };
 
 use generic_array::typenum;
@@ -13,10 +14,14 @@ fn main() {
 		([1., 0.].into(), [1.].into()),
 		([1., 1.].into(), [1.].into()),
 	];
-	last.teach(inputs.clone().into_iter(), 1000, |_, _| {});
+	let schedule = Linear {
+		start: 0.9,
+		end: 0.4,
+	};
+	last.teach(inputs.clone(), 1000, 1, schedule, |_, _| {});
 
 	for (input, output) in &inputs {
-		let result = last.calculate(&input);
+		let result = last.calculate(input);
 		println!(
 			"trained result of {:?} is {} should be {}",
 			input, result[0], output[0]
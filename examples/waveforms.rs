@@ -0,0 +1,94 @@
+use dumbnet::{
+	activation::{ReLu, Sigmoid},
+	conv1d::Conv1d,
+	layers::{InnerLayer, Network, OutputLayer},
+	schedule::Linear,
+};
+
+use generic_array::{
+	typenum::{U1, U2, U3, U32, U4, U48, U5, U8},
+	GenericArray,
+};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+
+use std::f32::consts::PI;
+
+// 4 filters of 5 samples, spread over 9, moved 2 samples at a time produce 4 * 12 outputs
+type Classifier = Conv1d<
+	ReLu,
+	U32,
+	U1,
+	U5,
+	U4,
+	U2,
+	U2,
+	U3,
+	InnerLayer<Sigmoid, U8, U48, U3, OutputLayer<Sigmoid, U3, U8>>,
+>;
+
+const SHAPES: [&str; 3] = ["sine", "square", "sawtooth"];
+
+/// a noisy wave of the given shape, with random frequency and phase
+fn wave<R: Rng>(shape: usize, rng: &mut R) -> (GenericArray<f32, U32>, GenericArray<f32, U3>) {
+	let cycles = rng.gen_range(1., 4.);
+	let phase = rng.gen_range(0., 1.);
+	let input = (0..32)
+		.map(|t| {
+			let position = (t as f32 / 32. * cycles + phase) % 1.;
+			let value = match shape {
+				0 => (position * 2. * PI).sin(),
+				1 if position < 0.5 => 1.,
+				1 => -1.,
+				_ => position * 2. - 1.,
+			};
+			value + rng.gen_range(-0.1, 0.1)
+		})
+		.collect();
+	let mut output = GenericArray::default();
+	output[shape] = 1.;
+	(input, output)
+}
+
+fn waves<R: Rng>(
+	count: usize,
+	rng: &mut R,
+) -> Vec<(GenericArray<f32, U32>, GenericArray<f32, U3>)> {
+	(0..count).map(|i| wave(i % SHAPES.len(), rng)).collect()
+}
+
+fn main() {
+	let mut rng = Pcg32::seed_from_u64(0);
+	let mut network = Classifier::push_with_rng(
+		InnerLayer::push_with_rng(OutputLayer::new_with_rng(&mut rng), &mut rng),
+		&mut rng,
+	);
+
+	let lesson = waves(300, &mut rng);
+	let schedule = Linear {
+		start: 0.5,
+		end: 0.05,
+	};
+	network.teach(lesson, 200, 10, schedule, |epoch, loss| {
+		if epoch % 20 == 0 {
+			println!("epoch {}: loss {}", epoch, loss);
+		}
+	});
+
+	let test = waves(90, &mut rng);
+	let correct = test
+		.iter()
+		.filter(|(input, output)| {
+			let result = network.calculate(input);
+			let guess = (0..3)
+				.max_by(|&a, &b| result[a].partial_cmp(&result[b]).unwrap())
+				.unwrap();
+			output[guess] == 1.
+		})
+		.count();
+	println!("recognized {} of {} unseen waveforms", correct, test.len());
+	for (shape, name) in SHAPES.iter().enumerate() {
+		let (input, _) = wave(shape, &mut rng);
+		println!("{:>8}: {:?}", name, network.calculate(&input));
+	}
+}
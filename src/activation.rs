@@ -1,5 +1,8 @@
 //! Activations for use in [layers](crate::layers)
 //!
+//! Most activations are an [Activation] that works on each neuron on its own, some like [SoftMax]
+//! are a [LayerActivation] that looks at the whole layer.
+//!
 //! The Enums in this module are empty and therefore not initializable. They are only used as type
 //! paramenters.
 use core::fmt::Debug;
@@ -28,7 +31,7 @@ impl Activation for Sigmoid {
 	#[inline(always)]
 	fn activate(input: f32) -> f32 {
 		// clamp to make the .exp() in sigmoid not go crazy
-		let clamped = input.max(-20.).min(20.);
+		let clamped = input.clamp(-20., 20.);
 		let res = 1. / (1. + (-clamped).exp());
 		debug_assert!(!res.is_nan());
 		res
@@ -53,13 +56,83 @@ impl Activation for ReLu {
 	fn derivate(activation: f32) -> f32 { if activation > 0. { 1. } else { 0.01 } }
 }
 
-#[doc(hidden)]
+/// Returns the input unchanged, for outputs that can take any value, like the mean and
+/// log-variance of a [VariationalAutoencoder](crate::autoencoder::VariationalAutoencoder).
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub enum SoftMax {}
+pub enum Identity {}
 
-impl Activation for SoftMax {
+impl Activation for Identity {
 	#[inline(always)]
-	fn activate(_input: f32) -> f32 { panic!() }
+	fn activate(input: f32) -> f32 { input }
 	#[inline(always)]
-	fn derivate(_activation: f32) -> f32 { panic!() }
+	fn derivate(_activation: f32) -> f32 { 1. }
+}
+
+/// An activation of a whole layer at once, where every output may depend on every input.
+///
+/// Every [Activation] is one, applied to each neuron on its own. It works on slices, so it fits
+/// layers of any shape.
+pub trait LayerActivation: Debug + Serialize + DeserializeOwned + Clone {
+	/// Inputs are the summed and weighted inputs of all neurons
+	///
+	/// Outputs are the outputs of all neurons, of the same length
+	fn activate_layer(inputs: &[f32], outputs: &mut [f32]);
+
+	/// The jacobian-vector product that carries the error of the outputs back to the inputs of
+	/// the activation, in place.
+	///
+	/// result\[j\] = sum over i of error\[i\] * d output\[i\] / d input\[j\]
+	///
+	/// outputs is the result of [activate_layer](LayerActivation::activate_layer) for inputs.
+	fn jvp(inputs: &[f32], outputs: &[f32], error: &mut [f32]);
+}
+
+impl<A: Activation> LayerActivation for A {
+	fn activate_layer(inputs: &[f32], outputs: &mut [f32]) {
+		debug_assert_eq!(inputs.len(), outputs.len());
+		for (output, &input) in outputs.iter_mut().zip(inputs) {
+			*output = A::activate(input);
+		}
+	}
+
+	fn jvp(_inputs: &[f32], outputs: &[f32], error: &mut [f32]) {
+		// each output only depends on its own input, so the jacobian is diagonal
+		error
+			.iter_mut()
+			.zip(outputs)
+			.for_each(|(error, &output)| *error *= A::derivate(output));
+	}
+}
+
+/// Turns the outputs into probabilities that sum up to 1.
+///
+/// e^input / sum(e^inputs)
+///
+/// Best used in the final layer together with
+/// [CategoricalCrossEntropy](crate::loss::CategoricalCrossEntropy).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum SoftMax {}
+
+impl LayerActivation for SoftMax {
+	fn activate_layer(inputs: &[f32], outputs: &mut [f32]) {
+		debug_assert_eq!(inputs.len(), outputs.len());
+		// for numerical stability we reduce stuff by the maximum input
+		let max = inputs.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+		for (output, input) in outputs.iter_mut().zip(inputs) {
+			*output = (input - max).exp();
+		}
+
+		let exp_sum = outputs.iter().sum::<f32>();
+		outputs.iter_mut().for_each(|o| *o /= exp_sum);
+	}
+
+	fn jvp(_inputs: &[f32], outputs: &[f32], error: &mut [f32]) {
+		// output * (error - sum(output * error))
+		// for categorical cross-entropy this boils down to correct_output - output
+		let weighted_sum: f32 = outputs.iter().zip(error.iter()).map(|(o, e)| o * e).sum();
+		error
+			.iter_mut()
+			.zip(outputs)
+			.for_each(|(e, o)| *e = o * (*e - weighted_sum));
+	}
 }
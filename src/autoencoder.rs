@@ -1,58 +1,793 @@
+//! An Autoencoder learns to compress its inputs and to restore them from the compressed code.
+//!
+//! The encoder is a network taking InOut inputs down to Compressed outputs, the decoder takes
+//! those back up to InOut. Both are trained together by showing the autoencoder the data it
+//! should compress, the decoder learns from its reconstruction and passes its error on to the
+//! encoder. For images the decoder can grow the code back to their size with the layers in
+//! [upsampling](crate::upsampling).
+//!
+//! Once trained, the encoder can run on its own to compress data, for example on the device
+//! collecting it, and the decoder wherever it gets decompressed. Inputs the autoencoder can not
+//! restore well are unlike anything it was trained on, see [report](Autoencoder::report).
+//!
+//! ## Regularization
+//! An autoencoder with enough neurons may just learn to copy its inputs. [Regularization] makes
+//! [teach](Autoencoder::teach) train a denoising autoencoder, which restores the clean inputs from
+//! corrupted ones, a sparse autoencoder, whose code neurons are mostly inactive, or both.
+//!
+//! ## Variational Autoencoders
+//! A [VariationalAutoencoder] encodes its inputs as a normal distribution instead of a single
+//! code, and learns to keep those close to a standard normal distribution. New outputs can then be
+//! [sampled](VariationalAutoencoder::sample) from random codes.
+use crate::{
+	initializer::standard_normal,
+	layers::{sealed, Differentiable, Network, AL, DEFAULT_SEED},
+	schedule::LearningRateSchedule,
+};
+use core::ops::Add;
+use generic_array::{typenum::Sum, GenericArray};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(
-    bound = "InOut: AL, Compressed: AL, EncoderNeurons: NL<InOut>, DecoderNeurons: NL<Compressed>"
-)]
+#[serde(bound = "InOut: AL, Compressed: AL")]
 pub struct Autoencoder<
-    InOut: AL,
-    Compressed: AL,
-    EncoderNeurons: NL<InOut>,
-    DecoderNeurons: NL<Compressed>,
-    Encoder: Layer<InOut, EncoderNeurons, Compressed>,
-    Decoder: Layer<Compressed, DecoderNeurons, InOut>,
+	InOut: AL,
+	Compressed: AL,
+	Encoder: Differentiable<InOut, Compressed>,
+	Decoder: Differentiable<Compressed, InOut>,
 > {
-    encoder: Encoder,
-    decoder: Decoder,
-    phantom: std::marker::PhantomData<(InOut, Compressed, EncoderNeurons, DecoderNeurons)>,
+	encoder: Encoder,
+	decoder: Decoder,
+	phantom: core::marker::PhantomData<(InOut, Compressed)>,
 }
+
+/// How well an [Autoencoder] restores some data, see [report](Autoencoder::report).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Reconstruction {
+	pub examples: usize,
+	/// average loss of the reconstructions
+	pub mean_loss: f32,
+	/// loss of the worst reconstruction
+	pub max_loss: f32,
+}
+
+/// How the inputs get corrupted while training a denoising autoencoder.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Noise {
+	/// adds normally distributed noise with this standard deviation to every input
+	Gaussian(f32),
+	/// sets every input to 0 with this probability
+	Masking(f32),
+}
+
+impl Noise {
+	/// a corrupted copy of input
+	pub fn corrupt<N: AL, R: Rng>(
+		&self,
+		input: &GenericArray<f32, N>,
+		rng: &mut R,
+	) -> GenericArray<f32, N> {
+		input
+			.iter()
+			.map(|&value| match *self {
+				Noise::Gaussian(deviation) => value + deviation * standard_normal(rng),
+				Noise::Masking(probability) => {
+					if rng.gen::<f32>() < probability {
+						0.
+					} else {
+						value
+					}
+				},
+			})
+			.collect()
+	}
+}
+
+/// A penalty on code neurons that are active more often than target on average.
+///
+/// It is the Kullback-Leibler divergence between target and the average activation of each code
+/// neuron, times weight. The average is taken over the whole lesson at the start of every
+/// iteration, so the code has to be between 0 and 1, like the output of a
+/// [Sigmoid](crate::activation::Sigmoid).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Sparsity {
+	pub target: f32,
+	pub weight: f32,
+}
+
+impl Sparsity {
+	/// keeps the logarithms of the divergence finite
+	const EPSILON: f32 = 1e-4;
+
+	/// the error of an activation of a code neuron with the average activation average
+	fn error(&self, average: f32) -> f32 {
+		let average = average.clamp(Self::EPSILON, 1. - Self::EPSILON);
+		// the gradient points uphill, so invert it to get the error
+		-self.weight * (-self.target / average + (1. - self.target) / (1. - average))
+	}
+}
+
+/// Options for [Autoencoder::teach], by default the autoencoder is trained to simply reconstruct
+/// its inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Regularization {
+	noise: Option<Noise>,
+	sparsity: Option<Sparsity>,
+	seed: u64,
+}
+
+impl Default for Regularization {
+	fn default() -> Self {
+		Self {
+			noise: None,
+			sparsity: None,
+			seed: DEFAULT_SEED,
+		}
+	}
+}
+
+impl Regularization {
+	/// corrupt the inputs with noise, and teach the autoencoder to reconstruct the clean ones
+	pub fn with_noise(mut self, noise: Noise) -> Self {
+		self.noise = Some(noise);
+		self
+	}
+
+	/// penalize code neurons that are active more often than target, see [Sparsity]
+	pub fn with_sparsity(mut self, target: f32, weight: f32) -> Self {
+		self.sparsity = Some(Sparsity { target, weight });
+		self
+	}
+
+	/// the seed of the rng drawing the noise
+	pub fn with_seed(mut self, seed: u64) -> Self {
+		self.seed = seed;
+		self
+	}
+}
+
+impl<
+		InOut: AL,
+		Compressed: AL,
+		Encoder: Differentiable<InOut, Compressed>,
+		Decoder: Differentiable<Compressed, InOut>,
+	> Autoencoder<InOut, Compressed, Encoder, Decoder>
+{
+	pub fn new(encoder: Encoder, decoder: Decoder) -> Self {
+		Self {
+			encoder,
+			decoder,
+			phantom: core::marker::PhantomData,
+		}
+	}
+
+	pub fn get_encoder(&self) -> &Encoder { &self.encoder }
+
+	pub fn get_encoder_mut(&mut self) -> &mut Encoder { &mut self.encoder }
+
+	pub fn get_decoder(&self) -> &Decoder { &self.decoder }
+
+	pub fn get_decoder_mut(&mut self) -> &mut Decoder { &mut self.decoder }
+
+	pub fn get_both(self) -> (Encoder, Decoder) { (self.encoder, self.decoder) }
+
+	/// compresses the input
+	pub fn encode(&self, input: &GenericArray<f32, InOut>) -> GenericArray<f32, Compressed> {
+		self.encoder.calculate(input)
+	}
+
+	/// restores an input from its compressed code
+	pub fn decode(&self, code: &GenericArray<f32, Compressed>) -> GenericArray<f32, InOut> {
+		self.decoder.calculate(code)
+	}
+
+	/// compresses and restores the input
+	pub fn reconstruct(&self, input: &GenericArray<f32, InOut>) -> GenericArray<f32, InOut> {
+		self.decode(&self.encode(input))
+	}
+
+	/// the loss of the decoder between the input and its reconstruction
+	pub fn reconstruction_loss(&self, input: &GenericArray<f32, InOut>) -> f32 {
+		self.evaluate(input, input)
+	}
+
+	/// the reconstruction losses over all of data
+	pub fn report<I: IntoIterator<Item = GenericArray<f32, InOut>>>(
+		&self,
+		data: I,
+	) -> Reconstruction {
+		let mut report = Reconstruction {
+			examples: 0,
+			mean_loss: 0.,
+			max_loss: 0.,
+		};
+		for input in data {
+			let loss = self.reconstruction_loss(&input);
+			report.examples += 1;
+			report.mean_loss += (loss - report.mean_loss) / report.examples as f32;
+			report.max_loss = report.max_loss.max(loss);
+		}
+		report
+	}
+
+	/// Trains encoder and decoder to reconstruct the lesson, like [Network::teach] with every input
+	/// as its own correct output.
+	///
+	/// regularization can make it a denoising or sparse autoencoder. The callback gets the
+	/// iteration and the average reconstruction loss of it, without any sparsity penalty but with
+	/// the weight penalties a [Regularizer](crate::regularizer::Regularizer) reports.
+	pub fn teach<S, F, I>(
+		&mut self,
+		lesson: I,
+		iterations: usize,
+		batch_size: usize,
+		mut schedule: S,
+		regularization: Regularization,
+		mut callback: F,
+	) where
+		S: LearningRateSchedule,
+		F: FnMut(usize, f32),
+		I: IntoIterator<Item = GenericArray<f32, InOut>>,
+		<I as IntoIterator>::IntoIter: Clone,
+	{
+		let lesson = lesson.into_iter();
+		let batch_size = batch_size.max(1);
+		let mut rng = Pcg32::seed_from_u64(regularization.seed);
+		for i in 0..iterations {
+			let speed = schedule.speed(i, iterations);
+			let sparsity = regularization
+				.sparsity
+				.map(|sparsity| (sparsity, self.average_code(lesson.clone())));
+
+			let mut avg_loss = 0f32;
+			let mut batch = 0;
+			for (pos, input) in lesson.clone().enumerate() {
+				let corrupted = match regularization.noise {
+					Some(noise) => noise.corrupt(&input, &mut rng),
+					None => input.clone(),
+				};
+				let loss = self.accumulate_regularized(&corrupted, &input, sparsity.as_ref());
+				avg_loss += (loss - avg_loss) / ((pos + 1) as f32);
+				batch += 1;
+				if batch == batch_size {
+					self.apply_batch(batch, speed);
+					batch = 0;
+				}
+			}
+			self.apply_batch(batch, speed);
+			let avg_loss = avg_loss + self._penalty();
+
+			schedule.end_epoch(avg_loss);
+			callback(i, avg_loss);
+		}
+	}
+
+	/// the average activation of each code neuron over data
+	fn average_code<I: Iterator<Item = GenericArray<f32, InOut>>>(
+		&self,
+		data: I,
+	) -> GenericArray<f32, Compressed> {
+		let mut average = GenericArray::<f32, Compressed>::default();
+		for (pos, input) in data.enumerate() {
+			for (average, code) in average.iter_mut().zip(&self.encode(&input)) {
+				*average += (code - *average) / ((pos + 1) as f32);
+			}
+		}
+		average
+	}
+
+	/// like [accumulate](Network::accumulate), reconstructing clean from input and adding the
+	/// sparsity penalty to the error of the code
+	fn accumulate_regularized(
+		&mut self,
+		input: &GenericArray<f32, InOut>,
+		clean: &GenericArray<f32, InOut>,
+		sparsity: Option<&(Sparsity, GenericArray<f32, Compressed>)>,
+	) -> f32 {
+		let decoder = &mut self.decoder;
+		let (_, loss) = self.encoder._accumulate_with(input, |code| {
+			let (mut error, loss) =
+				decoder._accumulate_with(code, |output| Decoder::_error(output, clean));
+			if let Some((sparsity, average)) = sparsity {
+				for (error, &average) in error.iter_mut().zip(average) {
+					*error += sparsity.error(average);
+				}
+			}
+			(error, loss)
+		});
+		loss
+	}
+}
+
 impl<
-        InOut: AL,
-        Compressed: AL,
-        EncoderNeurons: NL<InOut>,
-        DecoderNeurons: NL<Compressed>,
-        Encoder: Layer<InOut, EncoderNeurons, Compressed>,
-        Decoder: Layer<Compressed, DecoderNeurons, InOut>,
-    > Autoencoder<InOut, Compressed, EncoderNeurons, DecoderNeurons, Encoder, Decoder>
+		InOut: AL,
+		Compressed: AL,
+		Encoder: Differentiable<InOut, Compressed>,
+		Decoder: Differentiable<Compressed, InOut>,
+	> sealed::Sealed for Autoencoder<InOut, Compressed, Encoder, Decoder>
 {
-    pub fn new(encoder: Encoder, decoder: Decoder) -> Self {
-        Self {
-            encoder,
-            decoder,
-            phantom: std::marker::PhantomData::default(),
-        }
-    }
-    pub fn get_encoder(&self) -> &Encoder {
-        &self.encoder
-    }
-    pub fn get_encoder_mut(&mut self) -> &mut Encoder {
-        &mut self.encoder
-    }
-    pub fn get_decoder(&self) -> &Decoder {
-        &self.decoder
-    }
-    pub fn get_decoder_mut(&mut self) -> &mut Decoder {
-        &mut self.decoder
-    }
-    pub fn get_both(self) -> (Encoder, Decoder) {
-        (self.encoder, self.decoder)
-    }
-    fn teach<F: FnMut(usize, f32), I: IntoIterator<Item = GenericArray<f32, InOut>>>(
-        &mut self,
-        lesson: I,
-        iterations: usize,
-        mut callback: F,
-    ) where
-        <I as IntoIterator>::IntoIter: Clone,
-    {
-        unimplemented!()
-    }
+}
+
+/// The autoencoder is a network whose correct output is its input.
+impl<
+		InOut: AL,
+		Compressed: AL,
+		Encoder: Differentiable<InOut, Compressed>,
+		Decoder: Differentiable<Compressed, InOut>,
+	> Differentiable<InOut, InOut> for Autoencoder<InOut, Compressed, Encoder, Decoder>
+{
+	fn _calculate(&self, inputs: &GenericArray<f32, InOut>) -> GenericArray<f32, InOut> {
+		self.reconstruct(inputs)
+	}
+
+	fn _loss(output: &GenericArray<f32, InOut>, correct_output: &GenericArray<f32, InOut>) -> f32 {
+		Decoder::_loss(output, correct_output)
+	}
+
+	fn _error(
+		output: &GenericArray<f32, InOut>,
+		correct_output: &GenericArray<f32, InOut>,
+	) -> (GenericArray<f32, InOut>, f32) {
+		Decoder::_error(output, correct_output)
+	}
+
+	fn _accumulate_with<F>(
+		&mut self,
+		inputs: &GenericArray<f32, InOut>,
+		objective: F,
+	) -> (GenericArray<f32, InOut>, f32)
+	where
+		F: FnOnce(&GenericArray<f32, InOut>) -> (GenericArray<f32, InOut>, f32),
+	{
+		// the error of the code is the error of the decoders inputs
+		let decoder = &mut self.decoder;
+		self.encoder
+			._accumulate_with(inputs, |code| decoder._accumulate_with(code, objective))
+	}
+
+	fn _accumulate_batch<const B: usize, F>(
+		&mut self,
+		inputs: &[GenericArray<f32, InOut>; B],
+		objective: F,
+	) -> ([GenericArray<f32, InOut>; B], f32)
+	where
+		F: FnOnce(&[GenericArray<f32, InOut>; B]) -> ([GenericArray<f32, InOut>; B], f32),
+	{
+		let decoder = &mut self.decoder;
+		self.encoder
+			._accumulate_batch(inputs, |codes| decoder._accumulate_batch(codes, objective))
+	}
+
+	fn _apply_deltas(&mut self, batch_size: usize, speed: f32) {
+		self.encoder._apply_deltas(batch_size, speed);
+		self.decoder._apply_deltas(batch_size, speed);
+	}
+
+	fn _params_mut<F: FnMut(&mut f32, &mut f32)>(&mut self, f: &mut F) {
+		self.encoder._params_mut(f);
+		self.decoder._params_mut(f);
+	}
+
+	fn _penalty(&self) -> f32 { self.encoder._penalty() + self.decoder._penalty() }
+}
+
+/// An autoencoder whose encoder outputs the mean and the log-variance of a normal distribution
+/// for each of the Compressed code neurons, which are then drawn from it.
+///
+/// The encoder has twice Compressed outputs, first all means, then all log-variances. Its final
+/// layer should use the [Identity](crate::activation::Identity) activation so they can take any
+/// value.
+///
+/// Training minimizes the reconstruction loss of the decoder plus the Kullback-Leibler divergence
+/// of the distributions from a standard normal distribution, times
+/// [kl_weight](VariationalAutoencoder::with_kl_weight).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "InOut: AL, Compressed: AL")]
+pub struct VariationalAutoencoder<
+	InOut: AL,
+	Compressed: AL + Add<Compressed>,
+	Encoder: Differentiable<InOut, Sum<Compressed, Compressed>>,
+	Decoder: Differentiable<Compressed, InOut>,
+> where
+	Sum<Compressed, Compressed>: AL,
+{
+	encoder: Encoder,
+	decoder: Decoder,
+	kl_weight: f32,
+	phantom: core::marker::PhantomData<(InOut, Compressed)>,
+}
+
+impl<
+		InOut: AL,
+		Compressed: AL + Add<Compressed>,
+		Encoder: Differentiable<InOut, Sum<Compressed, Compressed>>,
+		Decoder: Differentiable<Compressed, InOut>,
+	> VariationalAutoencoder<InOut, Compressed, Encoder, Decoder>
+where
+	Sum<Compressed, Compressed>: AL,
+{
+	/// weighs the reconstruction loss and the divergence the same
+	pub fn new(encoder: Encoder, decoder: Decoder) -> Self {
+		Self {
+			encoder,
+			decoder,
+			kl_weight: 1.,
+			phantom: core::marker::PhantomData,
+		}
+	}
+
+	/// multiplies the divergence with kl_weight, lower values make for better reconstructions but
+	/// less normal codes
+	pub fn with_kl_weight(mut self, kl_weight: f32) -> Self {
+		self.kl_weight = kl_weight;
+		self
+	}
+
+	pub fn get_encoder(&self) -> &Encoder { &self.encoder }
+
+	pub fn get_decoder(&self) -> &Decoder { &self.decoder }
+
+	pub fn get_both(self) -> (Encoder, Decoder) { (self.encoder, self.decoder) }
+
+	/// the mean and log-variance of the code of input
+	pub fn encode(
+		&self,
+		input: &GenericArray<f32, InOut>,
+	) -> (GenericArray<f32, Compressed>, GenericArray<f32, Compressed>) {
+		split::<Compressed>(&self.encoder.calculate(input))
+	}
+
+	/// restores an input from a code
+	pub fn decode(&self, code: &GenericArray<f32, Compressed>) -> GenericArray<f32, InOut> {
+		self.decoder.calculate(code)
+	}
+
+	/// decodes the mean of the code of input
+	pub fn reconstruct(&self, input: &GenericArray<f32, InOut>) -> GenericArray<f32, InOut> {
+		self.decode(&self.encode(input).0)
+	}
+
+	/// generates a new output from a code drawn from a standard normal distribution
+	pub fn sample<R: Rng>(&self, rng: &mut R) -> GenericArray<f32, InOut> {
+		let code = (0..Compressed::to_usize())
+			.map(|_| standard_normal(rng))
+			.collect();
+		self.decode(&code)
+	}
+
+	/// the reconstruction losses over all of data, decoding the mean of each code
+	pub fn report<I: IntoIterator<Item = GenericArray<f32, InOut>>>(
+		&self,
+		data: I,
+	) -> Reconstruction {
+		let mut report = Reconstruction {
+			examples: 0,
+			mean_loss: 0.,
+			max_loss: 0.,
+		};
+		for input in data {
+			let loss = Decoder::_loss(&self.reconstruct(&input), &input);
+			report.examples += 1;
+			report.mean_loss += (loss - report.mean_loss) / report.examples as f32;
+			report.max_loss = report.max_loss.max(loss);
+		}
+		report
+	}
+
+	/// Adds the deltas for reconstructing input to the ones of the current batch, drawing the code
+	/// using rng.
+	///
+	/// returns the reconstruction loss plus the weighted divergence
+	pub fn accumulate<R: Rng>(&mut self, input: &GenericArray<f32, InOut>, rng: &mut R) -> f32 {
+		let noise = (0..Compressed::to_usize())
+			.map(|_| standard_normal(rng))
+			.collect();
+		self.accumulate_with_noise(input, &noise)
+	}
+
+	/// corrects the weights by the average of the deltas of the last batch_size
+	/// [accumulate](VariationalAutoencoder::accumulate) calls
+	pub fn apply_batch(&mut self, batch_size: usize, speed: f32) {
+		if batch_size > 0 {
+			self.encoder._apply_deltas(batch_size, speed);
+			self.decoder._apply_deltas(batch_size, speed);
+		}
+	}
+
+	/// Trains encoder and decoder to reconstruct the lesson, like [Autoencoder::teach].
+	///
+	/// The callback gets the iteration and the average loss of it, including the divergence and
+	/// the weight penalties a [Regularizer](crate::regularizer::Regularizer) reports.
+	pub fn teach<S, F, I, R>(
+		&mut self,
+		lesson: I,
+		iterations: usize,
+		batch_size: usize,
+		mut schedule: S,
+		rng: &mut R,
+		mut callback: F,
+	) where
+		S: LearningRateSchedule,
+		F: FnMut(usize, f32),
+		I: IntoIterator<Item = GenericArray<f32, InOut>>,
+		<I as IntoIterator>::IntoIter: Clone,
+		R: Rng,
+	{
+		let lesson = lesson.into_iter();
+		let batch_size = batch_size.max(1);
+		for i in 0..iterations {
+			let speed = schedule.speed(i, iterations);
+			let mut avg_loss = 0f32;
+			let mut batch = 0;
+			for (pos, input) in lesson.clone().enumerate() {
+				let loss = self.accumulate(&input, rng);
+				avg_loss += (loss - avg_loss) / ((pos + 1) as f32);
+				batch += 1;
+				if batch == batch_size {
+					self.apply_batch(batch, speed);
+					batch = 0;
+				}
+			}
+			self.apply_batch(batch, speed);
+			let avg_loss = avg_loss + self.encoder._penalty() + self.decoder._penalty();
+
+			schedule.end_epoch(avg_loss);
+			callback(i, avg_loss);
+		}
+	}
+
+	/// the reparameterisation trick: code = mean + deviation * noise, with noise drawn from a
+	/// standard normal distribution. That way the error of the code can pass through to the mean
+	/// and log-variance.
+	fn accumulate_with_noise(
+		&mut self,
+		input: &GenericArray<f32, InOut>,
+		noise: &GenericArray<f32, Compressed>,
+	) -> f32 {
+		let kl_weight = self.kl_weight;
+		let decoder = &mut self.decoder;
+		let (_, loss) = self.encoder._accumulate_with(input, |distribution| {
+			let (mean, log_variance) = split::<Compressed>(distribution);
+			let deviation: GenericArray<f32, Compressed> =
+				log_variance.iter().map(|v| (0.5 * v).exp()).collect();
+			let code: GenericArray<f32, Compressed> = mean
+				.iter()
+				.zip(&deviation)
+				.zip(noise)
+				.map(|((mean, deviation), noise)| mean + deviation * noise)
+				.collect();
+			let (code_error, loss) =
+				decoder._accumulate_with(&code, |output| Decoder::_error(output, input));
+
+			// -0.5 * sum(1 + log_variance - mean^2 - variance)
+			let mut divergence = 0.;
+			let mut error = GenericArray::<f32, Sum<Compressed, Compressed>>::default();
+			let (mean_error, log_variance_error) = error.split_at_mut(Compressed::to_usize());
+			for i in 0..Compressed::to_usize() {
+				let variance = deviation[i] * deviation[i];
+				divergence -= 0.5 * (1. + log_variance[i] - mean[i] * mean[i] - variance);
+				// the errors point downhill, the derivates of the divergence uphill
+				mean_error[i] = code_error[i] - kl_weight * mean[i];
+				log_variance_error[i] = code_error[i] * noise[i] * 0.5 * deviation[i]
+					- kl_weight * 0.5 * (variance - 1.);
+			}
+			(error, loss + kl_weight * divergence)
+		});
+		loss
+	}
+}
+
+/// splits the output of the encoder of a [VariationalAutoencoder] into mean and log-variance
+fn split<Compressed: AL + Add<Compressed>>(
+	distribution: &GenericArray<f32, Sum<Compressed, Compressed>>,
+) -> (GenericArray<f32, Compressed>, GenericArray<f32, Compressed>)
+where
+	Sum<Compressed, Compressed>: AL,
+{
+	let (mean, log_variance) = distribution.split_at(Compressed::to_usize());
+	(
+		GenericArray::clone_from_slice(mean),
+		GenericArray::clone_from_slice(log_variance),
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		activation::{Identity, Sigmoid},
+		gradcheck::{check, check_params},
+		layers::{default_rng, InnerLayer, OutputLayer},
+		loss::{Loss, Mse},
+		optimizer::Adam,
+		schedule::Constant,
+		testing::assert_batch_matches,
+	};
+	use generic_array::{
+		arr,
+		typenum::{U2, U4, U6, U64},
+	};
+
+	type Encoder = InnerLayer<Sigmoid, U4, U6, U2, OutputLayer<Sigmoid, U2, U4, Mse, Adam>, Adam>;
+	type Decoder = OutputLayer<Sigmoid, U6, U2, Mse, Adam>;
+
+	fn autoencoder() -> Autoencoder<U6, U2, Encoder, Decoder> {
+		let mut rng = default_rng();
+		let encoder = Encoder::push_with_rng(OutputLayer::new_with_rng(&mut rng), &mut rng);
+		Autoencoder::new(encoder, OutputLayer::new_with_rng(&mut rng))
+	}
+
+	/// one of four corners of a square in 6 dimensions, which fits into a code of 2
+	fn data() -> impl Iterator<Item = GenericArray<f32, U6>> + Clone {
+		(0..4).map(|i| {
+			let (a, b) = ((i & 1) as f32, (i >> 1) as f32);
+			arr![f32; a, b, 1. - a, 1. - b, a, b]
+		})
+	}
+
+	#[test]
+	fn learns_to_reconstruct() {
+		let mut autoencoder = autoencoder();
+		let before = autoencoder.report(data());
+		autoencoder.teach(
+			data(),
+			2000,
+			1,
+			Constant(0.01),
+			Regularization::default(),
+			|_, _| {},
+		);
+		let after = autoencoder.report(data());
+
+		assert_eq!(after.examples, 4);
+		assert!(after.mean_loss <= after.max_loss);
+		assert!(
+			after.max_loss < before.mean_loss / 10.,
+			"{:?} -> {:?}",
+			before,
+			after
+		);
+		for input in data() {
+			let restored = autoencoder.decode(&autoencoder.encode(&input));
+			assert_eq!(restored, autoencoder.reconstruct(&input));
+			for (restored, input) in restored.iter().zip(&input) {
+				assert!((restored - input).abs() < 0.2, "{:?} {:?}", restored, input);
+			}
+		}
+	}
+
+	#[test]
+	fn gradients() {
+		let input = data().nth(2).unwrap();
+		let result = check(&autoencoder(), &input, &input, 1e-2, 1e-2);
+		assert!(result.is_ok(), "{:?}", result);
+	}
+
+	#[test]
+	fn batches() {
+		let inputs = [0, 1, 2, 3].map(|i| data().nth(i).unwrap());
+		assert_batch_matches(&autoencoder(), &inputs, &inputs);
+	}
+
+	fn trained(regularization: Regularization) -> Autoencoder<U6, U2, Encoder, Decoder> {
+		let mut autoencoder = autoencoder();
+		autoencoder.teach(data(), 3000, 1, Constant(0.01), regularization, |_, _| {});
+		autoencoder
+	}
+
+	#[test]
+	fn corrupts_inputs() {
+		let input = GenericArray::<f32, U64>::from_exact_iter((0..64).map(|_| 1.)).unwrap();
+		let mut rng = default_rng();
+
+		let masked = Noise::Masking(0.25).corrupt(&input, &mut rng);
+		let zeros = masked.iter().filter(|&&v| v == 0.).count();
+		assert!(zeros > 8 && zeros < 24, "{}", zeros);
+		assert!(masked.iter().all(|&v| v == 0. || v == 1.));
+
+		let noisy = Noise::Gaussian(0.1).corrupt(&input, &mut rng);
+		let mean = noisy.iter().sum::<f32>() / 64.;
+		assert!((mean - 1.).abs() < 0.05, "{}", mean);
+		assert!(noisy.iter().all(|&v| v != 1.));
+		assert_eq!(Noise::Gaussian(0.).corrupt(&input, &mut rng), input);
+	}
+
+	#[test]
+	fn denoises() {
+		let noise = Noise::Masking(0.3);
+		let denoising = trained(Regularization::default().with_noise(noise));
+		let plain = trained(Regularization::default());
+
+		// loss of restoring the clean inputs from the same corrupted ones
+		let restore = |autoencoder: &Autoencoder<U6, U2, Encoder, Decoder>| {
+			let mut rng = default_rng();
+			let mut loss = 0.;
+			for _ in 0..20 {
+				for clean in data() {
+					let corrupted = noise.corrupt(&clean, &mut rng);
+					loss += autoencoder.evaluate(&corrupted, &clean);
+				}
+			}
+			loss
+		};
+		assert!(restore(&denoising) < restore(&plain));
+		assert!(denoising.report(data()).mean_loss < 0.05);
+	}
+
+	#[test]
+	fn sparse_code() {
+		let mean_code = |regularization| {
+			let autoencoder = trained(regularization);
+			let average = autoencoder.average_code(data());
+			average.iter().sum::<f32>() / 2.
+		};
+		let sparse = mean_code(Regularization::default().with_sparsity(0.1, 0.5));
+		let dense = mean_code(Regularization::default());
+		assert!(sparse < dense, "{} {}", sparse, dense);
+		assert!(sparse < 0.3, "{}", sparse);
+	}
+
+	type VaeEncoder =
+		InnerLayer<Sigmoid, U4, U6, U4, OutputLayer<Identity, U4, U4, Mse, Adam>, Adam>;
+	type Vae = VariationalAutoencoder<U6, U2, VaeEncoder, Decoder>;
+
+	fn vae() -> Vae {
+		let mut rng = default_rng();
+		let encoder = VaeEncoder::push_with_rng(OutputLayer::new_with_rng(&mut rng), &mut rng);
+		VariationalAutoencoder::new(encoder, OutputLayer::new_with_rng(&mut rng))
+	}
+
+	#[test]
+	fn variational_gradients() {
+		let input = data().nth(1).unwrap();
+		let noise = arr![f32; 0.3, -1.2];
+		let kl_weight = 0.5;
+		let loss = |vae: &Vae| {
+			let (mean, log_variance) = vae.encode(&input);
+			let mut code = mean;
+			let mut divergence = 0.;
+			for i in 0..2 {
+				code[i] += (0.5 * log_variance[i]).exp() * noise[i];
+				divergence +=
+					0.5 * (mean[i] * mean[i] + log_variance[i].exp() - 1. - log_variance[i]);
+			}
+			Mse::loss(&vae.decode(&code), &input) + kl_weight * divergence
+		};
+
+		let vae = vae().with_kl_weight(kl_weight);
+		let result = check_params(
+			&vae,
+			|vae, f| {
+				vae.encoder._params_mut(&mut |param, delta| f(param, delta));
+				vae.decoder._params_mut(&mut |param, delta| f(param, delta));
+			},
+			|vae| vae.accumulate_with_noise(&input, &noise),
+			loss,
+			1e-2,
+			1e-2,
+		);
+		let (_, accumulated) = result.unwrap();
+		assert!((accumulated - loss(&vae)).abs() < 1e-5);
+	}
+
+	#[test]
+	fn variational_learns_and_samples() {
+		let mut vae = vae().with_kl_weight(0.01);
+		let before = vae.report(data());
+		let mut rng = default_rng();
+		vae.teach(data(), 3000, 1, Constant(0.01), &mut rng, |_, _| {});
+		let after = vae.report(data());
+		assert!(
+			after.mean_loss < before.mean_loss / 3.,
+			"{:?} -> {:?}",
+			before,
+			after
+		);
+
+		let sample = vae.sample(&mut default_rng());
+		assert_eq!(sample, vae.sample(&mut default_rng()));
+		assert!(sample.iter().all(|v| (0. ..=1.).contains(v)));
+		assert_ne!(sample, vae.sample(&mut rng));
+	}
 }
@@ -0,0 +1,52 @@
+//! a Layer sliding a set of filters along a sequence with multiple channels, like samples of an
+//! accelerometer or an audio stream
+//!
+//! The filters move S steps at a time and can be spread out by a dilation, so they cover a longer
+//! stretch of the sequence without more weights. The length of the output is known at compile
+//! time, see [Conv1dLength].
+//!
+//! for generic layer documentation see [layers](crate::layers)
+use generic_array::typenum::{U0, U1};
+
+use crate::{
+	convolution::{ConvOutputSize, ConvWSize, ConvolutionalLayer, Valid},
+	optimizer::Sgd,
+};
+
+pub type Conv1dOutputSize<L, K, N, S, DL> = ConvOutputSize<L, U1, K, U1, N, U1, S, Valid, DL>;
+/// the length of each channel of the output
+pub type Conv1dLength<L, K, S, DL> = ConvWSize<L, K, S, U0, DL>;
+
+/// A [ConvolutionalLayer] over a sequence, which is an image one row high with the C channels as
+/// its depth.
+///
+/// Input and output are stored channel after channel, so index `(c, t)` is at `c * L + t`.
+/// Each of the N filters covers K steps of all channels and produces one [Conv1dLength] long
+/// channel of the output. The filters move S steps at a time, with DL - 1 steps between each of
+/// their weights.
+pub type Conv1d<A, L, C, K, N, S, DL, FinalOut, Next, O = Sgd> =
+	ConvolutionalLayer<A, L, U1, C, K, U1, N, U1, S, Valid, DL, FinalOut, Next, O>;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{activation::Sigmoid, gradcheck::check, layers::OutputLayer, testing::sample};
+	use generic_array::typenum::{Unsigned, U10, U18, U2, U3, U4, U8, U9};
+
+	type Head = OutputLayer<Sigmoid, U2, U8>;
+
+	#[test]
+	fn output_length() {
+		assert_eq!(Conv1dLength::<U10, U3, U1, U1>::to_usize(), 8);
+		assert_eq!(Conv1dLength::<U10, U3, U2, U1>::to_usize(), 4);
+		assert_eq!(Conv1dLength::<U10, U3, U1, U2>::to_usize(), 6);
+		assert_eq!(Conv1dOutputSize::<U9, U3, U4, U3, U2>::to_usize(), 8);
+	}
+
+	#[test]
+	fn gradients() {
+		let network = Conv1d::<Sigmoid, U9, U2, U3, U4, U3, U2, U2, Head>::push(OutputLayer::new());
+		let result = check(&network, &sample::<U18>(3), &sample(4), 1e-2, 1e-2);
+		assert!(result.is_ok(), "{:?}", result);
+	}
+}
@@ -1,344 +1,652 @@
-use core::ops::{Add, Div, Mul, Sub};
-use generic_array::typenum::bit::B1;
-use generic_array::typenum::{Add1, Diff, Prod, Quot, Unsigned};
-use generic_array::GenericArray;
-
-use crate::activation::Activation;
-use crate::layers::{Layer, AL, NL};
-
-use ndarray::{ArrayView3, ArrayViewMut3};
+//! a Layer applying a set of filters to a 3d input, like an image with multiple color channels
+//!
+//! The filters move SH rows and SW columns at a time. Their weights can be spread out by a
+//! dilation, and the input can get [padded](Padding) on all sides, so the filters also cover its
+//! edges. All of that is part of the type, so the sizes of the output are known at compile time.
+//!
+//! Other kinds of convolution, like the ones in [depthwise](crate::depthwise), are the same
+//! [Convolution] with their own [Connections] between inputs and outputs.
+//!
+//! for generic layer documentation see [layers](crate::layers)
+use core::{
+	fmt::Debug,
+	marker::PhantomData,
+	ops::{Add, Div, Mul, Sub},
+};
+use generic_array::{
+	typenum::{bit::B1, Add1, Diff, IsLess, Prod, Quot, Sub1, Sum, True, Unsigned, U0, U1, U2},
+	GenericArray,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+	activation::Activation,
+	initializer::{Initializer, Uniform},
+	layers::{apply_deltas, default_rng, map_batch, params_mut, sealed, Differentiable, AL, NL},
+	optimizer::{Optimizer, Sgd},
+};
+use rand::Rng;
+
+pub type ConvOutputSize<W, H, CW, CH, N, SH, SW, P, DL> = Prod<
+	Prod<
+		ConvHSize<H, CH, SH, <P as Padding>::Height, DL>,
+		ConvWSize<W, CW, SW, <P as Padding>::Width, DL>,
+	>,
+	N,
+>;
+/// the height of each plane of the output, for PH rows of padding above and below
+pub type ConvHSize<H, CH, SH, PH, DL> =
+	Add1<Quot<Diff<PaddedSize<H, PH>, DilatedSize<CH, DL>>, SH>>;
+/// the width of each plane of the output, for PW columns of padding left and right
+pub type ConvWSize<W, CW, SW, PW, DL> =
+	Add1<Quot<Diff<PaddedSize<W, PW>, DilatedSize<CW, DL>>, SW>>;
+/// the size of the input with P padding on both sides
+pub type PaddedSize<I, P> = Sum<I, Prod<P, U2>>;
+/// the area a filter of size C covers, with DL - 1 inputs between each of its weights
+pub type DilatedSize<C, DL> = Add1<Prod<Sub1<C>, DL>>;
+
+/// How the input of a [ConvolutionalLayer] gets extended beyond its edges.
+pub trait Padding: Debug + Clone + Serialize + DeserializeOwned {
+	/// rows added above and below the input
+	type Height: Unsigned;
+	/// columns added left and right of the input
+	type Width: Unsigned;
+
+	/// the index of the input the padded position reads from, position being an index into a
+	/// dimension of the input of the given size. None reads a 0.
+	fn source(position: isize, size: usize) -> Option<usize>;
+}
 
-type ConvOutputSize<W, H, CW, CH, N, S> = Prod<Prod<ConvHSize<H, CH, S>, ConvWSize<W, CW, S>>, N>;
-type ConvHSize<H, CH, S> = Add1<Quot<Diff<H, CH>, S>>;
-type ConvWSize<W, CW, S> = Add1<Quot<Diff<W, CW>, S>>;
+/// Pads PH rows and PW columns of zeros.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Zeros<PH, PW>(PhantomData<(PH, PW)>);
+
+impl<PH: Unsigned + Debug + Clone, PW: Unsigned + Debug + Clone> Padding for Zeros<PH, PW> {
+	type Height = PH;
+	type Width = PW;
+
+	fn source(position: isize, size: usize) -> Option<usize> {
+		if position < 0 || position as usize >= size {
+			None
+		} else {
+			Some(position as usize)
+		}
+	}
+}
 
+/// Pads PH rows and PW columns mirroring the input at its edges, without repeating the edge.
+///
+/// The padding has to be smaller than the input, see [PaddingFor].
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(
-    bound = "W: AL, H: AL, D: AL, CW: AL, CH: AL, N: AL, S: AL, FinalOut: AL, NextN: NL<ConvOutputSize<W, H, CW, CH, N, S>>, NextA: Activation"
-)]
-pub struct ConvolutionalLayer<
-    A: Activation,
-    // width of the input
-    W: AL,
-    // height of the input
-    H: AL,
-    // number of inputs/depth of input
-    D: AL,
-    // width of the filter
-    CW: AL,
-    // height of the filter
-    CH: AL,
-    // number of filters
-    N: NL<Prod<Prod<CH, CW>, D>>,
-    // stride of the filter (how many elements its moved on each application)
-    // sadly the currently used matrix library does not allow for having different H and W strides
-    S: AL,
-    FinalOut: AL,
-    NextN: NL<ConvOutputSize<W, H, CW, CH, N, S>>,
-    NextA: Activation,
-    Next: Layer<ConvOutputSize<W, H, CW, CH, N, S>, NextN, FinalOut, NextA>,
-> where
-    H: Mul<W>,
-    Prod<H, W>: Mul<D>,
-    Prod<Prod<H, W>, D>: AL,
-
-    CH: Mul<CW>,
-    Prod<CH, CW>: Mul<D>,
-    Prod<Prod<CH, CW>, D>: AL,
-
-    H: Sub<CH>,
-    Diff<H, CH>: Div<S>,
-    Quot<Diff<H, CH>, S>: Add<B1>,
-
-    W: Sub<CW>,
-    Diff<W, CW>: Div<S>,
-    Quot<Diff<W, CW>, S>: Add<B1>,
-
-    ConvHSize<H, CH, S>: Mul<ConvWSize<W, CW, S>>,
-    ConvHSize<H, CH, S>: AL,
-    ConvWSize<W, CW, S>: AL,
-    Prod<ConvHSize<H, CH, S>, ConvWSize<W, CW, S>>: Mul<N>,
-
-    ConvOutputSize<W, H, CW, CH, N, S>: AL + NL<Prod<Prod<H, W>, D>>,
+#[serde(bound = "")]
+pub struct Reflect<PH, PW>(PhantomData<(PH, PW)>);
+
+impl<PH: Unsigned + Debug + Clone, PW: Unsigned + Debug + Clone> Padding for Reflect<PH, PW> {
+	type Height = PH;
+	type Width = PW;
+
+	fn source(position: isize, size: usize) -> Option<usize> {
+		let last = size as isize - 1;
+		let reflected = if position < 0 {
+			-position
+		} else if position > last {
+			2 * last - position
+		} else {
+			position
+		};
+		Some(reflected as usize)
+	}
+}
+
+/// A [Padding] that fits an input of H rows and W columns.
+///
+/// [Reflect] padding only fits inputs larger than itself, so larger padding fails to compile.
+///
+/// ```compile_fail
+/// use dumbnet::{
+///     activation::Sigmoid,
+///     convolution::{ConvolutionalLayer, Reflect},
+///     layers::OutputLayer,
+/// };
+/// use generic_array::typenum::{U1, U3};
+///
+/// // 3 rows can not reflect 3 rows of padding
+/// type Layer<Next> =
+///     ConvolutionalLayer<Sigmoid, U3, U3, U1, U1, U1, U1, U1, U1, Reflect<U3, U1>, U1, U1, Next>;
+/// let layer = Layer::push(OutputLayer::<Sigmoid, U1, _>::new());
+/// ```
+pub trait PaddingFor<H, W>: Padding {}
+
+impl<PH: Unsigned + Debug + Clone, PW: Unsigned + Debug + Clone, H, W> PaddingFor<H, W>
+	for Zeros<PH, PW>
 {
-    phantom: core::marker::PhantomData<(A, W, H, D, CW, CH, S, FinalOut, NextN, NextA, Next)>,
-    // there are N filters each CH * CW * D in size
-    filter: GenericArray<GenericArray<f32, Prod<Prod<CH, CW>, D>>, N>,
-    bias: GenericArray<f32, ConvOutputSize<W, H, CW, CH, N, S>>,
-    next: Next,
 }
 
-impl<
-        A: Activation,
-        W: AL,
-        H: AL,
-        D: AL,
-        CW: AL,
-        CH: AL,
-        N: NL<Prod<Prod<CH, CW>, D>>,
-        S: AL,
-        FinalOut: AL,
-        NextN: NL<ConvOutputSize<W, H, CW, CH, N, S>>,
-        NextA: Activation,
-        Next: Layer<ConvOutputSize<W, H, CW, CH, N, S>, NextN, FinalOut, NextA>,
-    > ConvolutionalLayer<A, W, H, D, CW, CH, N, S, FinalOut, NextN, NextA, Next>
+impl<PH, PW, H, W> PaddingFor<H, W> for Reflect<PH, PW>
 where
-    H: Mul<W>,
-    Prod<H, W>: Mul<D>,
-    Prod<H, W>: AL,
-    Prod<Prod<H, W>, D>: AL,
-
-    CH: Mul<CW>,
-    Prod<CH, CW>: Mul<D>,
-    Prod<Prod<CH, CW>, D>: AL,
-
-    H: Sub<CH>,
-    Diff<H, CH>: Div<S>,
-    Quot<Diff<H, CH>, S>: Add<B1>,
-
-    W: Sub<CW>,
-    Diff<W, CW>: Div<S>,
-    Quot<Diff<W, CW>, S>: Add<B1>,
-
-    ConvHSize<H, CH, S>: Mul<ConvWSize<W, CW, S>>,
-    ConvHSize<H, CH, S>: AL,
-    ConvWSize<W, CW, S>: AL,
-    Prod<ConvHSize<H, CH, S>, ConvWSize<W, CW, S>>: Mul<N>,
-
-    ConvOutputSize<W, H, CW, CH, N, S>: AL + NL<Prod<Prod<H, W>, D>>,
+	PH: Unsigned + Debug + Clone + IsLess<H, Output = True>,
+	PW: Unsigned + Debug + Clone + IsLess<W, Output = True>,
 {
-    pub fn push(next: Next) -> Self {
-        use rand::Rng;
-        let mut rng = rand::rngs::OsRng;
-
-        let mut new = Self {
-            next,
-            phantom: core::marker::PhantomData::default(),
-            filter: GenericArray::default(),
-            bias: GenericArray::default(),
-        };
-
-        for filter in new.filter.iter_mut() {
-            for weight in filter.iter_mut() {
-                *weight = rng.gen_range(-1.0f32, 1.0f32);
-            }
-        }
-
-        for neuron in new.bias.iter_mut() {
-            *neuron = rng.gen_range(-0.5, 0.5);
-        }
-
-        new
-    }
 }
 
+/// No padding, the filters only cover positions where they fit completely.
+pub type Valid = Zeros<U0, U0>;
+
+/// Zero padding that keeps the size of the input for odd CH * CW filters with dilation DL and a
+/// stride of 1.
+pub type Same<CH, CW, DL = U1> = Zeros<SameSize<CH, DL>, SameSize<CW, DL>>;
+/// the padding on each side for [Same]
+pub type SameSize<C, DL> = Quot<Sub1<DilatedSize<C, DL>>, U2>;
+
+/// Which input a [Convolution] multiplies with which filter weight for each of its outputs.
+///
+/// Implementing this is all a new kind of convolution needs, weighting, backpropagation and the
+/// updates of filters and biases only follow the connections.
+pub trait Connections: Debug + Clone {
+	/// the size of the input
+	type Input: AL;
+	/// the size of the output, each output has its own bias
+	type Output: AL;
+	/// the number of weights of a single filter
+	type Weights: AL;
+	/// the number of filters
+	type Filters: NL<Self::Weights>;
+
+	/// calls `f(output, filter, weight, input)` for every multiplication of the convolution, with
+	/// `weight` being the index inside of `filter`.
+	///
+	/// forward and backward pass both use this so they can not disagree on the indexing.
+	fn for_each_connection<F: FnMut(usize, usize, usize, usize)>(f: F);
+}
+
+/// The connections of a [ConvolutionalLayer], see there for the meaning of the parameters.
+#[derive(Debug, Clone)]
+pub struct Full<W, H, D, CW, CH, N, SH, SW, P, DL>(
+	PhantomData<(W, H, D, CW, CH, N, SH, SW, P, DL)>,
+);
+
 impl<
-        A: Activation,
-        W: AL,
-        H: AL,
-        D: AL,
-        CW: AL,
-        CH: AL,
-        N: NL<Prod<Prod<CH, CW>, D>>,
-        S: AL,
-        FinalOut: AL,
-        NextN: NL<ConvOutputSize<W, H, CW, CH, N, S>>,
-        NextA: Activation,
-        Next: Layer<ConvOutputSize<W, H, CW, CH, N, S>, NextN, FinalOut, NextA>,
-    > Layer<Prod<Prod<H, W>, D>, ConvOutputSize<W, H, CW, CH, N, S>, FinalOut, A>
-    for ConvolutionalLayer<A, W, H, D, CW, CH, N, S, FinalOut, NextN, NextA, Next>
+		W: AL,
+		H: AL,
+		D: AL,
+		CW: AL,
+		CH: AL,
+		N: NL<Prod<Prod<CH, CW>, D>>,
+		SH: AL,
+		SW: AL,
+		P: PaddingFor<H, W>,
+		DL: AL,
+	> Connections for Full<W, H, D, CW, CH, N, SH, SW, P, DL>
 where
-    H: Mul<W>,
-    Prod<H, W>: Mul<D>,
-    Prod<H, W>: AL,
-    Prod<Prod<H, W>, D>: AL,
+	H: Mul<W>,
+	Prod<H, W>: Mul<D>,
+	Prod<Prod<H, W>, D>: AL,
+
+	CH: Mul<CW>,
+	Prod<CH, CW>: Mul<D>,
+	Prod<Prod<CH, CW>, D>: AL,
+
+	CH: Sub<B1>,
+	Sub1<CH>: Mul<DL>,
+	Prod<Sub1<CH>, DL>: Add<B1>,
+	P::Height: Mul<U2>,
+	H: Add<Prod<P::Height, U2>>,
+	PaddedSize<H, P::Height>: Sub<DilatedSize<CH, DL>>,
+	Diff<PaddedSize<H, P::Height>, DilatedSize<CH, DL>>: Div<SH>,
+	Quot<Diff<PaddedSize<H, P::Height>, DilatedSize<CH, DL>>, SH>: Add<B1>,
+
+	CW: Sub<B1>,
+	Sub1<CW>: Mul<DL>,
+	Prod<Sub1<CW>, DL>: Add<B1>,
+	P::Width: Mul<U2>,
+	W: Add<Prod<P::Width, U2>>,
+	PaddedSize<W, P::Width>: Sub<DilatedSize<CW, DL>>,
+	Diff<PaddedSize<W, P::Width>, DilatedSize<CW, DL>>: Div<SW>,
+	Quot<Diff<PaddedSize<W, P::Width>, DilatedSize<CW, DL>>, SW>: Add<B1>,
+
+	ConvHSize<H, CH, SH, P::Height, DL>: Mul<ConvWSize<W, CW, SW, P::Width, DL>>,
+	ConvHSize<H, CH, SH, P::Height, DL>: AL,
+	ConvWSize<W, CW, SW, P::Width, DL>: AL,
+	Prod<ConvHSize<H, CH, SH, P::Height, DL>, ConvWSize<W, CW, SW, P::Width, DL>>: Mul<N>,
+
+	ConvOutputSize<W, H, CW, CH, N, SH, SW, P, DL>: AL,
+{
+	type Input = Prod<Prod<H, W>, D>;
+	type Output = ConvOutputSize<W, H, CW, CH, N, SH, SW, P, DL>;
+	type Weights = Prod<Prod<CH, CW>, D>;
+	type Filters = N;
+
+	#[inline(always)]
+	fn for_each_connection<F: FnMut(usize, usize, usize, usize)>(mut f: F) {
+		let width = W::to_usize();
+		let height = H::to_usize();
+		let depth = D::to_usize();
+		let filter_width = CW::to_usize();
+		let filter_height = CH::to_usize();
+		let stride_height = SH::to_usize();
+		let stride_width = SW::to_usize();
+		let padding_height = P::Height::to_usize() as isize;
+		let padding_width = P::Width::to_usize() as isize;
+		let dilation = DL::to_usize();
+		let output_height = ConvHSize::<H, CH, SH, P::Height, DL>::to_usize();
+		let output_width = ConvWSize::<W, CW, SW, P::Width, DL>::to_usize();
+		let number_of_filters = N::to_usize();
+
+		for filter in 0..number_of_filters {
+			for out_y in 0..output_height {
+				for out_x in 0..output_width {
+					let output = (filter * output_height + out_y) * output_width + out_x;
+					for d in 0..depth {
+						for filter_y in 0..filter_height {
+							let padded_y = (out_y * stride_height + filter_y * dilation) as isize;
+							// weights over zero padding have nothing to connect to
+							let y = match P::source(padded_y - padding_height, height) {
+								Some(y) => y,
+								None => continue,
+							};
+							for filter_x in 0..filter_width {
+								let padded_x =
+									(out_x * stride_width + filter_x * dilation) as isize;
+								let x = match P::source(padded_x - padding_width, width) {
+									Some(x) => x,
+									None => continue,
+								};
+								let weight =
+									(d * filter_height + filter_y) * filter_width + filter_x;
+								let input = (d * height + y) * width + x;
+								f(output, filter, weight, input);
+							}
+						}
+					}
+				}
+			}
+		}
+	}
+}
 
-    CH: Mul<CW>,
-    Prod<CH, CW>: Mul<D>,
-    Prod<Prod<CH, CW>, D>: AL,
+/// A layer applying the filters of a [Connections] pattern to its input, followed by the
+/// activation A.
+///
+/// Use one of its aliases like [ConvolutionalLayer] instead of spelling out the connections.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Convolution<
+	A: Activation,
+	C: Connections,
+	FinalOut: AL,
+	Next: Differentiable<C::Output, FinalOut>,
+	O: Optimizer = Sgd,
+> {
+	phantom: PhantomData<(A, C, FinalOut, O)>,
+	filter: GenericArray<GenericArray<f32, C::Weights>, C::Filters>,
+	bias: GenericArray<f32, C::Output>,
+	filter_state: O::State<GenericArray<GenericArray<f32, C::Weights>, C::Filters>>,
+	bias_state: O::State<GenericArray<f32, C::Output>>,
+	// deltas of the current batch
+	#[serde(skip)]
+	filter_deltas: GenericArray<GenericArray<f32, C::Weights>, C::Filters>,
+	#[serde(skip)]
+	bias_deltas: GenericArray<f32, C::Output>,
+	next: Next,
+}
 
-    H: Sub<CH>,
-    Diff<H, CH>: Div<S>,
-    Quot<Diff<H, CH>, S>: Add<B1>,
+/// Input and output are stored depth-first, so index `(d, y, x)` is at `(d * H + y) * W + x`.
+/// Each filter produces one `ConvHSize * ConvWSize` plane of the output, the planes are stored one
+/// after another.
+///
+/// The parameters are
+/// - A: the activation
+/// - W, H, D: width, height and depth (number of channels) of the input
+/// - CW, CH: width and height of each filter, which covers the whole depth
+/// - N: the number of filters
+/// - SH, SW: vertical and horizontal stride, how many rows and columns the filter is moved on
+///   each application
+/// - P: the [Padding] around the input
+/// - DL: the dilation of the filter, 1 for filters covering adjacent inputs
+pub type ConvolutionalLayer<A, W, H, D, CW, CH, N, SH, SW, P, DL, FinalOut, Next, O = Sgd> =
+	Convolution<A, Full<W, H, D, CW, CH, N, SH, SW, P, DL>, FinalOut, Next, O>;
 
-    W: Sub<CW>,
-    Diff<W, CW>: Div<S>,
-    Quot<Diff<W, CW>, S>: Add<B1>,
+impl<
+		A: Activation,
+		C: Connections,
+		FinalOut: AL,
+		Next: Differentiable<C::Output, FinalOut>,
+		O: Optimizer,
+	> Convolution<A, C, FinalOut, Next, O>
+{
+	/// Pushes this layer on top of an existing layer.
+	pub fn push(next: Next) -> Self { Self::push_with_rng(next, &mut default_rng()) }
+
+	/// like [push](Convolution::push), drawing the initial weights from rng
+	pub fn push_with_rng<R: Rng>(next: Next, rng: &mut R) -> Self {
+		Self::push_with_init::<Uniform, R>(next, rng)
+	}
+
+	/// like [push](Convolution::push), drawing the initial weights from rng using the
+	/// [Initializer] I, with each filter counting as a neuron
+	pub fn push_with_init<I: Initializer, R: Rng>(next: Next, rng: &mut R) -> Self {
+		let mut new = Self {
+			next,
+			phantom: PhantomData,
+			filter: GenericArray::default(),
+			bias: GenericArray::default(),
+			filter_state: Default::default(),
+			bias_state: Default::default(),
+			filter_deltas: GenericArray::default(),
+			bias_deltas: GenericArray::default(),
+		};
+
+		I::weights(&mut new.filter, rng);
+		I::bias(&mut new.bias, rng);
+		new
+	}
+
+	pub(crate) fn weight(
+		&self,
+		inputs: &GenericArray<f32, C::Input>,
+	) -> GenericArray<f32, C::Output> {
+		// each output starts out as its bias, then the filter gets added on top
+		let mut outputs = self.bias.clone();
+		C::for_each_connection(|output, filter, weight, input| {
+			outputs[output] += self.filter[filter][weight] * inputs[input];
+		});
+		outputs
+	}
+
+	pub(crate) fn step(
+		&self,
+		inputs: &GenericArray<f32, C::Output>,
+	) -> GenericArray<f32, C::Output> {
+		inputs
+			.into_iter()
+			.map(|&input| A::activate(input))
+			.collect()
+	}
+
+	fn store_deltas(
+		&mut self,
+		deltas: GenericArray<f32, C::Output>,
+		inputs: &GenericArray<f32, C::Input>,
+	) {
+		// the filter is shared by all outputs of its plane, so it collects all of their deltas
+		let filter_deltas = &mut self.filter_deltas;
+		C::for_each_connection(|output, filter, weight, input| {
+			filter_deltas[filter][weight] += deltas[output] * inputs[input];
+		});
+		self.bias_deltas
+			.iter_mut()
+			.zip(deltas.iter())
+			.for_each(|(bias, delta)| *bias += delta);
+	}
+
+	fn pre_error(&self, deltas: &GenericArray<f32, C::Output>) -> GenericArray<f32, C::Input> {
+		// every input gets the deltas of all the outputs it contributed to, weighted by the
+		// filter weight it was multiplied with
+		let mut pre_errors = GenericArray::<f32, C::Input>::default();
+		C::for_each_connection(|output, filter, weight, input| {
+			pre_errors[input] += self.filter[filter][weight] * deltas[output];
+		});
+		pre_errors
+	}
+}
 
-    ConvHSize<H, CH, S>: Mul<ConvWSize<W, CW, S>>,
-    ConvHSize<H, CH, S>: AL,
-    ConvWSize<W, CW, S>: AL,
-    Prod<ConvHSize<H, CH, S>, ConvWSize<W, CW, S>>: Mul<N>,
+impl<
+		A: Activation,
+		C: Connections,
+		FinalOut: AL,
+		Next: Differentiable<C::Output, FinalOut>,
+		O: Optimizer,
+	> sealed::Sealed for Convolution<A, C, FinalOut, Next, O>
+{
+}
 
-    ConvOutputSize<W, H, CW, CH, N, S>: AL + NL<Prod<Prod<H, W>, D>>,
+impl<
+		A: Activation,
+		C: Connections,
+		FinalOut: AL,
+		Next: Differentiable<C::Output, FinalOut>,
+		O: Optimizer,
+	> Differentiable<C::Input, FinalOut> for Convolution<A, C, FinalOut, Next, O>
 {
-    fn calculate(
-        &self,
-        inputs: &GenericArray<f32, Prod<Prod<H, W>, D>>,
-    ) -> GenericArray<f32, FinalOut> {
-        let own_output = self.step(&self.weight(inputs));
-        self.next.calculate(&own_output)
-    }
-
-    fn weight(
-        &self,
-        inputs: &GenericArray<f32, Prod<Prod<H, W>, D>>,
-    ) -> GenericArray<f32, ConvOutputSize<W, H, CW, CH, N, S>> {
-        let width = <W as Unsigned>::to_usize();
-        let height = <H as Unsigned>::to_usize();
-        let depth = <D as Unsigned>::to_usize();
-        let filter_width = <CW as Unsigned>::to_usize();
-        let filter_height = <CH as Unsigned>::to_usize();
-        let stride = <S as Unsigned>::to_usize();
-
-        let input = ArrayView3::from_shape((depth, height, width), inputs)
-            .expect("input sizes are checked at compile time");
-
-        self.filter
-            .iter()
-            .flat_map(|arr_filter| {
-                let filter =
-                    ArrayView3::from_shape((depth, filter_height, filter_width), arr_filter)
-                        .expect("filter sizes are checked at compile time");
-                // each filter takes the whole depth
-                input
-                    .windows((depth, filter_height, filter_width))
-                    .into_iter()
-                    .step_by(stride)
-                    .map(move |v| (&v * &filter).sum())
-            })
-            .zip(&self.bias)
-            .map(|(value, bias)| value + bias)
-            // filter results are just stored one after another
-            // depth is the highest dimension
-            .collect()
-    }
-
-    fn step(
-        &self,
-        inputs: &GenericArray<f32, ConvOutputSize<W, H, CW, CH, N, S>>,
-    ) -> GenericArray<f32, ConvOutputSize<W, H, CW, CH, N, S>> {
-        inputs
-            .into_iter()
-            .map(|&input| A::activate(input))
-            .collect()
-    }
-
-    fn _get_error(
-        &mut self,
-        output: GenericArray<f32, ConvOutputSize<W, H, CW, CH, N, S>>,
-        correct_output: &GenericArray<f32, FinalOut>,
-        speed: f32,
-    ) -> (
-        GenericArray<f32, ConvOutputSize<W, H, CW, CH, N, S>>,
-        GenericArray<f32, FinalOut>,
-    ) {
-        self.next.backprop(&output, correct_output, speed)
-    }
-
-    fn _apply_deltas(
-        &mut self,
-        mut deltas: GenericArray<f32, ConvOutputSize<W, H, CW, CH, N, S>>,
-        inputs: &GenericArray<f32, Prod<Prod<H, W>, D>>,
-        speed: f32,
-    ) {
-        let width = <W as Unsigned>::to_usize();
-        let height = <H as Unsigned>::to_usize();
-        let depth = <D as Unsigned>::to_usize();
-        let filter_width = <CW as Unsigned>::to_usize();
-        let filter_height = <CH as Unsigned>::to_usize();
-        let stride = <S as Unsigned>::to_usize();
-        let output_height = <ConvHSize<H, CH, S> as Unsigned>::to_usize();
-        let output_width = <ConvWSize<W, CW, S> as Unsigned>::to_usize();
-        let number_of_filters = <N as Unsigned>::to_usize();
-
-        let input_matrix = ArrayView3::from_shape((depth, height, width), inputs)
-            .expect("error sizes are checked at compile time");
-
-        deltas.iter_mut().for_each(|d| *d *= speed);
-        self.bias
-            .iter_mut()
-            .zip(deltas.iter())
-            .for_each(|(bias, delta)| *bias += delta);
-
-        let delta_matrix =
-            ArrayView3::from_shape((number_of_filters, output_height, output_width), &deltas)
-                .expect("error sizes are checked at compile time");
-
-        self.filter
-            .iter_mut()
-            .zip(delta_matrix.outer_iter())
-            .for_each(|(filter, deltas)| {
-                let mut filter =
-                    ArrayViewMut3::from_shape((depth, filter_height, filter_width), filter)
-                        .expect("filter sizes are checked at compile time");
-                input_matrix
-                    .windows((depth, filter_height, filter_width))
-                    .into_iter()
-                    .step_by(stride)
-                    .zip(deltas.iter())
-                    .fold(&mut filter, |filter, (input_activation, &delta)| {
-                        filter.scaled_add(delta, &input_activation);
-                        filter
-                    });
-            });
-    }
-
-    fn _pre_error(
-        &self,
-        deltas: &GenericArray<f32, ConvOutputSize<W, H, CW, CH, N, S>>,
-    ) -> GenericArray<f32, Prod<Prod<H, W>, D>> {
-        let width = <W as Unsigned>::to_usize();
-        let height = <H as Unsigned>::to_usize();
-        let depth = <D as Unsigned>::to_usize();
-        let filter_width = <CW as Unsigned>::to_usize();
-        let filter_height = <CH as Unsigned>::to_usize();
-        let stride = <S as Unsigned>::to_usize();
-        let output_height = <ConvHSize<H, CH, S> as Unsigned>::to_usize();
-        let output_width = <ConvWSize<W, CW, S> as Unsigned>::to_usize();
-        let number_of_filters = <N as Unsigned>::to_usize();
-
-        let mut pre_errors = GenericArray::default();
-        // this is basically a type hint
-        if false {
-            return pre_errors;
-        }
-        use core::cell::Cell;
-        let errors_cell = Cell::from_mut(pre_errors.as_mut_slice());
-        let error_cells = errors_cell.as_slice_of_cells();
-
-        let error_matrix = ArrayView3::from_shape((depth, height, width), error_cells)
-            .expect("error sizes are checked at compile time");
-        let delta_matrix =
-            ArrayView3::from_shape((number_of_filters, output_height, output_width), deltas)
-                .expect("error sizes are checked at compile time");
-
-        // sidenote: i spent literally hours looking up smart matrix maths to do this,
-        // but i did not find anything usable
-        // sidenote2: tensorflow code is fucking unreadable
-        // sidenote3: this is crazy slow and very likely buggy
-        //
-        // the deltas have one 2d matrix per filter
-        // first zip the matching ones together
-        self.filter
-            .iter()
-            .zip(delta_matrix.outer_iter())
-            .for_each(|(filter, delta)| {
-                // apply the filter to the error_matrix for each delta-field
-                error_matrix
-                    .windows((depth, filter_height, filter_width))
-                    .into_iter()
-                    .step_by(stride)
-                    .zip(delta.iter())
-                    .for_each(|(e, &d)| {
-                        // matrix lib does not want to play nice with Cell
-                        // so we have to manually do the loops
-                        e.iter().zip(filter.iter()).for_each(|(e, f)| {
-                            e.set(e.get() + (d * f));
-                        });
-                    });
-            });
-
-        pre_errors
-    }
+	fn _calculate(&self, inputs: &GenericArray<f32, C::Input>) -> GenericArray<f32, FinalOut> {
+		let own_output = self.step(&self.weight(inputs));
+		self.next._calculate(&own_output)
+	}
+
+	fn _loss(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> f32 {
+		Next::_loss(output, correct_output)
+	}
+
+	fn _error(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> (GenericArray<f32, FinalOut>, f32) {
+		Next::_error(output, correct_output)
+	}
+
+	fn _accumulate_with<F>(
+		&mut self,
+		inputs: &GenericArray<f32, C::Input>,
+		objective: F,
+	) -> (GenericArray<f32, C::Input>, f32)
+	where
+		F: FnOnce(&GenericArray<f32, FinalOut>) -> (GenericArray<f32, FinalOut>, f32),
+	{
+		let weighted_inputs = self.weight(inputs);
+		let output = self.step(&weighted_inputs);
+		let (mut error, loss) = self.next._accumulate_with(&output, objective);
+		error
+			.iter_mut()
+			.zip(&weighted_inputs)
+			.for_each(|(error, &input)| *error *= A::derivate(A::activate(input)));
+		let pre_error = self.pre_error(&error);
+		self.store_deltas(error, inputs);
+		(pre_error, loss)
+	}
+
+	fn _accumulate_batch<const B: usize, F>(
+		&mut self,
+		inputs: &[GenericArray<f32, C::Input>; B],
+		objective: F,
+	) -> ([GenericArray<f32, C::Input>; B], f32)
+	where
+		F: FnOnce(&[GenericArray<f32, FinalOut>; B]) -> ([GenericArray<f32, FinalOut>; B], f32),
+	{
+		let weighted_inputs = inputs.each_ref().map(|inputs| self.weight(inputs));
+		let outputs = weighted_inputs
+			.each_ref()
+			.map(|weighted| self.step(weighted));
+		let (errors, loss) = self.next._accumulate_batch(&outputs, objective);
+		let pre_errors = map_batch(errors, |i, mut error| {
+			error
+				.iter_mut()
+				.zip(&weighted_inputs[i])
+				.for_each(|(error, &input)| *error *= A::derivate(A::activate(input)));
+			let pre_error = self.pre_error(&error);
+			self.store_deltas(error, &inputs[i]);
+			pre_error
+		});
+		(pre_errors, loss)
+	}
+
+	fn _apply_deltas(&mut self, batch_size: usize, speed: f32) {
+		apply_deltas::<O, _>(
+			&mut self.filter,
+			&mut self.filter_state,
+			&mut self.filter_deltas,
+			batch_size,
+			speed,
+		);
+		apply_deltas::<O, _>(
+			&mut self.bias,
+			&mut self.bias_state,
+			&mut self.bias_deltas,
+			batch_size,
+			speed,
+		);
+		self.next._apply_deltas(batch_size, speed);
+	}
+
+	fn _params_mut<F: FnMut(&mut f32, &mut f32)>(&mut self, f: &mut F) {
+		params_mut(&mut self.filter, &mut self.filter_deltas, f);
+		params_mut(&mut self.bias, &mut self.bias_deltas, f);
+		self.next._params_mut(f);
+	}
+
+	fn _penalty(&self) -> f32 { self.next._penalty() }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{activation::Sigmoid, layers::OutputLayer, testing::sample};
+	use generic_array::typenum::{U1, U18, U2, U24, U3, U4, U40, U42, U5, U50, U8};
+
+	fn assert_close<L: AL>(a: &GenericArray<f32, L>, b: &GenericArray<f32, L>) {
+		for (a, b) in a.iter().zip(b) {
+			assert!((a - b).abs() < 1e-5, "{:?} != {:?}", a, b);
+		}
+	}
+
+	// builds tests comparing a convolution to a dense layer with the unrolled filters as weights
+	macro_rules! compare_with_dense {
+		($name:ident, $connections:ty, $input:ty, $output:ty) => {
+			mod $name {
+				use super::*;
+
+				type Conv =
+					Convolution<Sigmoid, $connections, U1, OutputLayer<Sigmoid, U1, $output>>;
+				type Dense = OutputLayer<Sigmoid, $output, $input>;
+
+				fn layers() -> (Conv, Dense) {
+					let conv = Conv::push(OutputLayer::new());
+					let mut dense = Dense::new();
+					dense.weights = GenericArray::default();
+					dense.bias = conv.bias.clone();
+					<$connections>::for_each_connection(|output, filter, weight, input| {
+						// reflected inputs can meet the same output through several weights
+						dense.weights[output][input] += conv.filter[filter][weight];
+					});
+					(conv, dense)
+				}
+
+				#[test]
+				fn weight() {
+					let (conv, dense) = layers();
+					let input = sample(1);
+					assert_close(&conv.weight(&input), &dense.weight(&input));
+				}
+
+				#[test]
+				fn pre_error() {
+					let (conv, dense) = layers();
+					let deltas = sample(2);
+					assert_close(&conv.pre_error(&deltas), &dense.pre_error(&deltas));
+				}
+
+				#[test]
+				fn apply_deltas() {
+					let (mut conv, mut dense) = layers();
+					let (old_conv, old_dense) = (conv.clone(), dense.clone());
+					let input = sample(3);
+					let deltas = sample(4);
+					conv.store_deltas(deltas.clone(), &input);
+					conv._apply_deltas(1, 0.3);
+					dense.store_deltas(deltas, &input);
+					dense._apply_deltas(1, 0.3);
+
+					assert_close(&conv.bias, &dense.bias);
+
+					// a shared filter weight moves by the sum of all the dense weights it was
+					// unrolled into
+					let mut expected = old_conv.filter.clone();
+					<$connections>::for_each_connection(|output, filter, weight, input| {
+						expected[filter][weight] +=
+							dense.weights[output][input] - old_dense.weights[output][input];
+					});
+					for (filter, expected) in conv.filter.iter().zip(&expected) {
+						assert_close(filter, expected);
+					}
+				}
+			}
+		};
+	}
+
+	// non-square so width and height can not be mixed up
+	compare_with_dense!(
+		valid,
+		Full<U5, U4, U2, U3, U2, U2, U1, U1, Valid, U1>,
+		U40,
+		U18
+	);
+
+	compare_with_dense!(
+		strided,
+		Full<U5, U5, U2, U3, U3, U2, U2, U2, Valid, U1>,
+		U50,
+		U8
+	);
+
+	compare_with_dense!(
+		zero_padded,
+		Full<U5, U4, U2, U3, U2, U2, U2, U1, Zeros<U1, U2>, U1>,
+		U40,
+		U42
+	);
+
+	compare_with_dense!(
+		same,
+		Full<U5, U4, U2, U3, U3, U2, U1, U1, Same<U3, U3>, U1>,
+		U40,
+		U40
+	);
+
+	compare_with_dense!(
+		reflected_and_dilated,
+		Full<U5, U4, U2, U3, U2, U2, U1, U1, Reflect<U1, U1>, U2>,
+		U40,
+		U24
+	);
+
+	#[test]
+	fn output_size() {
+		assert_eq!(
+			ConvOutputSize::<U5, U4, U3, U2, U2, U1, U1, Valid, U1>::to_usize(),
+			18
+		);
+		assert_eq!(
+			ConvOutputSize::<U5, U5, U3, U3, U2, U2, U2, Valid, U1>::to_usize(),
+			8
+		);
+		assert_eq!(ConvHSize::<U5, U4, U1, U0, U1>::to_usize(), 2);
+		// strides, padding and dilation each only count in their own direction
+		assert_eq!(ConvHSize::<U4, U2, U2, U1, U1>::to_usize(), 3);
+		assert_eq!(ConvWSize::<U5, U3, U1, U2, U1>::to_usize(), 7);
+		assert_eq!(ConvHSize::<U4, U2, U1, U1, U2>::to_usize(), 4);
+		assert_eq!(DilatedSize::<U3, U3>::to_usize(), 7);
+	}
+
+	#[test]
+	fn same_keeps_the_size() {
+		type Plane<P> = ConvOutputSize<U8, U5, U3, U5, U1, U1, U1, P, U1>;
+		assert_eq!(Plane::<Same<U5, U3>>::to_usize(), 40);
+		assert_eq!(
+			ConvOutputSize::<U8, U5, U3, U3, U1, U1, U1, Same<U3, U3, U2>, U2>::to_usize(),
+			40
+		);
+	}
+
+	#[test]
+	fn reflects_at_the_edges() {
+		for (position, expected) in (-2..7).zip(&[2, 1, 0, 1, 2, 3, 4, 3, 2]) {
+			assert_eq!(Reflect::<U2, U2>::source(position, 5), Some(*expected));
+		}
+		assert_eq!(Zeros::<U1, U1>::source(-1, 5), None);
+		assert_eq!(Zeros::<U1, U1>::source(5, 5), None);
+		assert_eq!(Zeros::<U1, U1>::source(4, 5), Some(4));
+	}
 }
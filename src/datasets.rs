@@ -0,0 +1,202 @@
+//! Loading of training data, only available with the `std` feature.
+//!
+//! Currently this reads the IDX format used by [MNIST](http://yann.lecun.com/exdb/mnist/) and
+//! Fashion-MNIST: a few magic bytes, the big endian size of each dimension and then the raw data.
+use generic_array::{
+	typenum::{U10, U784},
+	GenericArray,
+};
+use std::{
+	fs::File,
+	io::{self, BufReader, Read},
+	path::Path,
+	vec::Vec,
+};
+
+/// the contents of an IDX file holding unsigned bytes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Idx {
+	/// the size of each dimension, the first one is the number of items
+	pub dimensions: Vec<usize>,
+	pub data: Vec<u8>,
+}
+
+fn invalid(message: &str) -> io::Error { io::Error::new(io::ErrorKind::InvalidData, message) }
+
+/// the number of elements of the dimensions, or None if it doesn't fit into usize
+fn size(dimensions: &[usize]) -> Option<usize> {
+	dimensions
+		.iter()
+		.try_fold(1usize, |size, &dimension| size.checked_mul(dimension))
+}
+
+impl Idx {
+	pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+		let mut magic = [0u8; 4];
+		reader.read_exact(&mut magic)?;
+		if magic[0] != 0 || magic[1] != 0 {
+			return Err(invalid("not an idx file"));
+		}
+		// other types are not used by the mnist files
+		if magic[2] != 0x08 {
+			return Err(invalid("only unsigned byte idx files are supported"));
+		}
+
+		let mut dimensions = Vec::with_capacity(magic[3] as usize);
+		for _ in 0..magic[3] {
+			let mut size = [0u8; 4];
+			reader.read_exact(&mut size)?;
+			dimensions.push(u32::from_be_bytes(size) as usize);
+		}
+
+		let len = size(&dimensions).ok_or_else(|| invalid("idx dimensions are too large"))?;
+		// the header can't be trusted with the allocation, the read grows it as data arrives
+		let mut data = Vec::new();
+		reader.take(len as u64).read_to_end(&mut data)?;
+		if data.len() != len {
+			return Err(invalid("idx file is shorter than its dimensions say"));
+		}
+		Ok(Self { dimensions, data })
+	}
+
+	pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		Self::read(BufReader::new(File::open(path)?))
+	}
+
+	/// the number of items, which is the size of the first dimension
+	pub fn len(&self) -> usize { self.dimensions.first().cloned().unwrap_or(0) }
+
+	pub fn is_empty(&self) -> bool { self.len() == 0 }
+}
+
+/// 28x28 pixel images of one of 10 classes, like the handwritten digits of MNIST or the clothes of
+/// Fashion-MNIST.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mnist {
+	images: Idx,
+	labels: Idx,
+}
+
+impl Mnist {
+	const PIXELS: usize = 28 * 28;
+
+	pub fn new(images: Idx, labels: Idx) -> io::Result<Self> {
+		if size(images.dimensions.get(1..).unwrap_or(&[])) != Some(Self::PIXELS) {
+			return Err(invalid("images are not 28x28 pixels"));
+		}
+		if labels.dimensions.len() != 1 {
+			return Err(invalid("labels are not one-dimensional"));
+		}
+		if images.len() != labels.len() {
+			return Err(invalid("number of images and labels differ"));
+		}
+		if labels.data.iter().any(|&label| label >= 10) {
+			return Err(invalid("label out of range"));
+		}
+		Ok(Self { images, labels })
+	}
+
+	/// reads the images and labels from two idx files, for example `train-images-idx3-ubyte` and
+	/// `train-labels-idx1-ubyte`
+	pub fn open<P: AsRef<Path>, Q: AsRef<Path>>(images: P, labels: Q) -> io::Result<Self> {
+		Self::new(Idx::open(images)?, Idx::open(labels)?)
+	}
+
+	pub fn len(&self) -> usize { self.labels.len() }
+
+	pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+	/// the images with their pixels scaled to 0..1 and their labels one-hot encoded
+	pub fn iter(
+		&self,
+	) -> impl Iterator<Item = (GenericArray<f32, U784>, GenericArray<f32, U10>)> + Clone + '_ {
+		self.images
+			.data
+			.chunks(Self::PIXELS)
+			.zip(&self.labels.data)
+			.map(|(image, &label)| {
+				let image = image.iter().map(|&pixel| f32::from(pixel) / 255.).collect();
+				let mut label_array = GenericArray::default();
+				label_array[label as usize] = 1.;
+				(image, label_array)
+			})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn idx(dimensions: &[u32], data: &[u8]) -> Vec<u8> {
+		let mut bytes = vec![0, 0, 0x08, dimensions.len() as u8];
+		for dimension in dimensions {
+			bytes.extend_from_slice(&dimension.to_be_bytes());
+		}
+		bytes.extend_from_slice(data);
+		bytes
+	}
+
+	#[test]
+	fn reads_idx() {
+		let bytes = idx(&[2, 3], &[1, 2, 3, 4, 5, 6]);
+		let parsed = Idx::read(&bytes[..]).unwrap();
+		assert_eq!(parsed.dimensions, vec![2, 3]);
+		assert_eq!(parsed.data, vec![1, 2, 3, 4, 5, 6]);
+		assert_eq!(parsed.len(), 2);
+
+		assert!(Idx::read(&bytes[..bytes.len() - 1]).is_err());
+		assert!(Idx::read(&[1, 0, 0x08, 0][..]).is_err());
+		assert!(Idx::read(&[0, 0, 0x0D, 0][..]).is_err());
+	}
+
+	#[test]
+	fn rejects_oversized_dimensions() {
+		let bytes = idx(&[u32::MAX, u32::MAX, u32::MAX], &[1, 2, 3]);
+		let error = Idx::read(&bytes[..]).unwrap_err();
+		assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+		// huge but representable sizes fail on the missing data instead of allocating it upfront
+		let bytes = idx(&[u32::MAX, 2], &[1, 2, 3]);
+		assert!(Idx::read(&bytes[..]).is_err());
+	}
+
+	#[test]
+	fn reads_mnist_files() {
+		let mut pixels = vec![0u8; 2 * 784];
+		pixels[0] = 255;
+		pixels[784 + 783] = 51;
+
+		let dir = std::env::temp_dir();
+		let images = dir.join(format!("dumbnet-test-images-{}", std::process::id()));
+		let labels = dir.join(format!("dumbnet-test-labels-{}", std::process::id()));
+		std::fs::write(&images, idx(&[2, 28, 28], &pixels)).unwrap();
+		std::fs::write(&labels, idx(&[2], &[7, 0])).unwrap();
+		let mnist = Mnist::open(&images, &labels);
+		std::fs::remove_file(&images).unwrap();
+		std::fs::remove_file(&labels).unwrap();
+
+		let mnist = mnist.unwrap();
+		assert_eq!(mnist.len(), 2);
+		let examples: Vec<_> = mnist.iter().collect();
+		assert_eq!(examples[0].0[0], 1.);
+		assert_eq!(examples[0].0[1], 0.);
+		assert_eq!(examples[1].0[783], 0.2);
+		assert_eq!(examples[0].1.iter().position(|&l| l == 1.), Some(7));
+		assert_eq!(examples[1].1.iter().sum::<f32>(), 1.);
+		assert_eq!(examples[1].1[0], 1.);
+	}
+
+	#[test]
+	fn rejects_mismatches() {
+		let images = Idx::read(&idx(&[1, 28, 28], &[0; 784])[..]).unwrap();
+		let small = Idx::read(&idx(&[1, 2, 2], &[0; 4])[..]).unwrap();
+		let one = Idx::read(&idx(&[1], &[3])[..]).unwrap();
+		let two = Idx::read(&idx(&[2], &[3, 4])[..]).unwrap();
+		let out_of_range = Idx::read(&idx(&[1], &[10])[..]).unwrap();
+
+		assert!(Mnist::new(images.clone(), one.clone()).is_ok());
+		assert!(Mnist::new(small, one).is_err());
+		assert!(Mnist::new(images.clone(), two).is_err());
+		assert!(Mnist::new(images, out_of_range).is_err());
+	}
+}
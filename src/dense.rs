@@ -0,0 +1,667 @@
+//! # Const Generic Layers
+//!
+//! The sizes of these layers are plain numbers instead of typenum types, and they work on
+//! `[f32; N]` instead of GenericArrays. `Dense<Sigmoid, 2, 3>` is a fully connected layer taking 2
+//! inputs into 3 neurons.
+//!
+//! Just like with [layers](crate::layers) you start with the final layer and
+//! [push](Dense::push) layers on top of it:
+//!
+//! ```
+//! use dumbnet::{
+//!     activation::Sigmoid,
+//!     dense::{to_array, to_generic, Dense},
+//!     layers::Network,
+//! };
+//!
+//! let mut network = Dense::<Sigmoid, 2, 3>::new().push(Dense::<Sigmoid, 3, 1>::new());
+//! network.backprop(&to_generic(&[0., 1.]), &to_generic(&[1.]), 0.5);
+//! let output: [f32; 1] = to_array(&network.calculate(&to_generic(&[0., 1.])));
+//! ```
+//!
+//! Each size maps to the typenum number of a GenericArray of the same size, see [Size], which
+//! makes the layers a [Network](crate::layers::Network) like any other. They train with a
+//! [Trainer](crate::trainer::Trainer), can be checked with [gradcheck](crate::gradcheck) and can
+//! be stacked together with GenericArray based layers. [to_generic] and [to_array] convert the
+//! inputs and outputs. Sizes of up to 1024 are supported.
+//!
+//! ## Migration
+//! Networks made of [OutputLayer]s and [InnerLayer]s can be converted using [TryFrom]. The weights
+//! and the [Regularizer] are kept, the state of the optimizer starts over. Since the layouts are
+//! the same, the serialized weights of an [OutputLayer] can also be read as a [Dense] layer
+//! directly, as long as the optimizer does not keep any state.
+use crate::{
+	activation::LayerActivation,
+	initializer::{Initializer, Uniform, Weights},
+	layers::{
+		apply_deltas, default_rng, map_batch, params_mut, sealed, Differentiable, InnerLayer,
+		OutputLayer, AL, NL,
+	},
+	loss::{Loss, Mse},
+	optimizer::{Optimizer, Params, Sgd},
+	regularizer::Regularizer,
+};
+use core::{
+	convert::TryFrom,
+	fmt::{self, Debug},
+	marker::PhantomData,
+	ops::{Deref, DerefMut},
+};
+use generic_array::{typenum, GenericArray};
+use rand::Rng;
+use serde::{
+	de::{self, SeqAccess, Visitor},
+	ser::SerializeTuple,
+	Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// An array of any size that serializes just like a GenericArray of the same size.
+///
+/// serde only supports arrays of up to 32 elements.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Array<T, const N: usize>(pub [T; N]);
+
+type Vector<const N: usize> = Array<f32, N>;
+type Matrix<const IN: usize, const OUT: usize> = Array<Array<f32, IN>, OUT>;
+
+impl<T: Default, const N: usize> Default for Array<T, N> {
+	fn default() -> Self { Array(core::array::from_fn(|_| T::default())) }
+}
+
+impl<T, const N: usize> Deref for Array<T, N> {
+	type Target = [T; N];
+	fn deref(&self) -> &[T; N] { &self.0 }
+}
+
+impl<T, const N: usize> DerefMut for Array<T, N> {
+	fn deref_mut(&mut self) -> &mut [T; N] { &mut self.0 }
+}
+
+impl<T: Serialize, const N: usize> Serialize for Array<T, N> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let mut tuple = serializer.serialize_tuple(N)?;
+		for value in self.iter() {
+			tuple.serialize_element(value)?;
+		}
+		tuple.end()
+	}
+}
+
+struct ArrayVisitor<T, const N: usize>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de> + Default, const N: usize> Visitor<'de> for ArrayVisitor<T, N> {
+	type Value = Array<T, N>;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		write!(formatter, "an array of length {}", N)
+	}
+
+	fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Array<T, N>, S::Error> {
+		let mut out = Array::<T, N>::default();
+		for (i, value) in out.iter_mut().enumerate() {
+			*value = seq
+				.next_element()?
+				.ok_or_else(|| de::Error::invalid_length(i, &self))?;
+		}
+		Ok(out)
+	}
+}
+
+impl<'de, T: Deserialize<'de> + Default, const N: usize> Deserialize<'de> for Array<T, N> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		deserializer.deserialize_tuple(N, ArrayVisitor(PhantomData))
+	}
+}
+
+impl<const N: usize> Params for Vector<N> {
+	fn values(&self) -> impl Iterator<Item = &f32> { self.iter() }
+	fn values_mut(&mut self) -> impl Iterator<Item = &mut f32> { self.iter_mut() }
+}
+
+impl<const IN: usize, const OUT: usize> Params for Matrix<IN, OUT> {
+	fn values(&self) -> impl Iterator<Item = &f32> { self.iter().flat_map(|n| n.iter()) }
+	fn values_mut(&mut self) -> impl Iterator<Item = &mut f32> {
+		self.iter_mut().flat_map(|n| n.iter_mut())
+	}
+}
+
+impl<const IN: usize, const OUT: usize> Weights for Matrix<IN, OUT> {
+	fn inputs(&self) -> usize { IN }
+	fn neurons(&self) -> usize { OUT }
+	fn weight(&self, neuron: usize, input: usize) -> f32 { self[neuron][input] }
+	fn weight_mut(&mut self, neuron: usize, input: usize) -> &mut f32 { &mut self[neuron][input] }
+}
+
+/// The const generic size N, see [ToUInt].
+#[derive(Debug, Clone, Copy)]
+pub struct Const<const N: usize>;
+
+/// Maps a const generic size to the typenum number GenericArrays of the same size use.
+///
+/// Implemented for sizes of up to 1024, like the mapping of typenum itself. Every size is an impl
+/// the compiler has to go through on each build, so larger sizes are left out. Layers with more
+/// inputs or neurons can be built and converted, but are no [Network](crate::layers::Network).
+#[diagnostic::on_unimplemented(
+	message = "the const generic size {Self} is not mapped to a typenum number",
+	note = "only sizes of up to 1024 can be used with the Network traits"
+)]
+pub trait ToUInt {
+	type Output: AL;
+}
+
+include!(concat!(env!("OUT_DIR"), "/sizes.rs"));
+
+/// the typenum number for the const generic size N
+pub type Size<const N: usize> = <Const<N> as ToUInt>::Output;
+
+/// copies array into a GenericArray of the same size
+pub fn to_generic<const N: usize>(array: &[f32; N]) -> GenericArray<f32, Size<N>>
+where
+	Const<N>: ToUInt,
+{
+	GenericArray::clone_from_slice(array)
+}
+
+/// copies a GenericArray into an array of the same size
+pub fn to_array<const N: usize>(generic: &GenericArray<f32, Size<N>>) -> [f32; N]
+where
+	Const<N>: ToUInt,
+{
+	let mut array = [0.; N];
+	array.copy_from_slice(generic);
+	array
+}
+
+/// A fully connected layer with IN inputs and OUT neurons.
+///
+/// On its own it is the final layer of a network, comparing its output using the [Loss] L. Use
+/// [push](Dense::push) to put it on top of other layers, which leaves L unused.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Dense<
+	A: LayerActivation,
+	const IN: usize,
+	const OUT: usize,
+	L: Loss = Mse,
+	O: Optimizer = Sgd,
+> {
+	weights: Matrix<IN, OUT>,
+	bias: Vector<OUT>,
+	weight_state: O::State<Matrix<IN, OUT>>,
+	bias_state: O::State<Vector<OUT>>,
+	regularizer: Regularizer,
+	// deltas of the current batch
+	#[serde(skip)]
+	weight_deltas: Matrix<IN, OUT>,
+	#[serde(skip)]
+	bias_deltas: Vector<OUT>,
+	phantom: PhantomData<(A, L, O)>,
+}
+
+/// A layer on top of the layers below it, see [Dense::push].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stack<Top, Next> {
+	top: Top,
+	next: Next,
+}
+
+impl<A: LayerActivation, const IN: usize, const OUT: usize, L: Loss, O: Optimizer>
+	Dense<A, IN, OUT, L, O>
+{
+	pub fn new() -> Self { Self::new_with_rng(&mut default_rng()) }
+
+	/// draws the initial weights from rng using the [Uniform] initializer
+	pub fn new_with_rng<R: Rng>(rng: &mut R) -> Self { Self::new_with_init::<Uniform, R>(rng) }
+
+	/// draws the initial weights from rng using the [Initializer] I
+	pub fn new_with_init<I: Initializer, R: Rng>(rng: &mut R) -> Self {
+		let mut out = Self::zeroed();
+		I::weights(&mut out.weights, rng);
+		I::bias(&mut *out.bias, rng);
+		out
+	}
+
+	fn zeroed() -> Self {
+		Self {
+			weights: Default::default(),
+			bias: Default::default(),
+			weight_state: Default::default(),
+			bias_state: Default::default(),
+			regularizer: Regularizer::default(),
+			weight_deltas: Default::default(),
+			bias_deltas: Default::default(),
+			phantom: PhantomData,
+		}
+	}
+
+	/// regularizes the weights of this layer using regularizer
+	pub fn with_regularizer(self, regularizer: Regularizer) -> Self {
+		Self {
+			regularizer,
+			..self
+		}
+	}
+
+	/// Pushes this layer on top of next, which takes the outputs of this layer as its inputs.
+	pub fn push<Next>(self, next: Next) -> Stack<Self, Next> { Stack { top: self, next } }
+
+	/// calculates the input to the activation functions for the neurons
+	fn weight(&self, inputs: &[f32; IN]) -> [f32; OUT] {
+		let mut out = self.bias.0;
+		for (out, neuron) in out.iter_mut().zip(self.weights.iter()) {
+			*out += neuron
+				.iter()
+				.zip(inputs)
+				.map(|(weight, input)| weight * input)
+				.sum::<f32>();
+		}
+		out
+	}
+
+	/// runs only this layers calculation, not recursing to deeper layers
+	fn step(&self, inputs: &[f32; IN]) -> ([f32; OUT], [f32; OUT]) {
+		let weighted_inputs = self.weight(inputs);
+		let mut output = [0.; OUT];
+		A::activate_layer(&weighted_inputs, &mut output);
+		(weighted_inputs, output)
+	}
+
+	/// carries the error back through the activation to get the deltas, stores them and returns
+	/// the error of the inputs
+	fn backward(
+		&mut self,
+		inputs: &[f32; IN],
+		weighted_inputs: &[f32; OUT],
+		output: &[f32; OUT],
+		mut error: [f32; OUT],
+	) -> [f32; IN] {
+		A::jvp(weighted_inputs, output, &mut error);
+
+		let mut pre_error = [0.; IN];
+		for ((neuron, deltas), &delta) in self
+			.weights
+			.iter()
+			.zip(self.weight_deltas.iter_mut())
+			.zip(&error)
+		{
+			for (((pre_error, weight), weight_delta), input) in pre_error
+				.iter_mut()
+				.zip(neuron.iter())
+				.zip(deltas.iter_mut())
+				.zip(inputs)
+			{
+				*pre_error += weight * delta;
+				*weight_delta += delta * input;
+			}
+		}
+		for (bias, delta) in self.bias_deltas.iter_mut().zip(&error) {
+			*bias += delta;
+		}
+		pre_error
+	}
+
+	fn apply_own_deltas(&mut self, batch_size: usize, speed: f32) {
+		self.regularizer.apply_deltas::<O, _>(
+			&mut self.weights,
+			&mut self.weight_state,
+			&mut self.weight_deltas,
+			batch_size,
+			speed,
+		);
+		apply_deltas::<O, _>(
+			&mut self.bias,
+			&mut self.bias_state,
+			&mut self.bias_deltas,
+			batch_size,
+			speed,
+		);
+	}
+}
+
+impl<A: LayerActivation, const IN: usize, const OUT: usize, L: Loss, O: Optimizer> Default
+	for Dense<A, IN, OUT, L, O>
+{
+	fn default() -> Self { Self::new() }
+}
+
+impl<A: LayerActivation, const IN: usize, const OUT: usize, L: Loss, O: Optimizer> sealed::Sealed
+	for Dense<A, IN, OUT, L, O>
+{
+}
+
+impl<A: LayerActivation, const IN: usize, const OUT: usize, L: Loss, O: Optimizer>
+	Differentiable<Size<IN>, Size<OUT>> for Dense<A, IN, OUT, L, O>
+where
+	Const<IN>: ToUInt,
+	Const<OUT>: ToUInt,
+{
+	fn _calculate(&self, inputs: &GenericArray<f32, Size<IN>>) -> GenericArray<f32, Size<OUT>> {
+		to_generic(&self.step(&to_array(inputs)).1)
+	}
+
+	fn _loss(
+		output: &GenericArray<f32, Size<OUT>>,
+		correct_output: &GenericArray<f32, Size<OUT>>,
+	) -> f32 {
+		L::loss(output, correct_output)
+	}
+
+	fn _error(
+		output: &GenericArray<f32, Size<OUT>>,
+		correct_output: &GenericArray<f32, Size<OUT>>,
+	) -> (GenericArray<f32, Size<OUT>>, f32) {
+		// the gradient points uphill, so invert it to get the error
+		let mut error = GenericArray::default();
+		L::gradient(output, correct_output, &mut error);
+		error.iter_mut().for_each(|e| *e = -*e);
+		(error, L::loss(output, correct_output))
+	}
+
+	fn _accumulate_with<F>(
+		&mut self,
+		inputs: &GenericArray<f32, Size<IN>>,
+		objective: F,
+	) -> (GenericArray<f32, Size<IN>>, f32)
+	where
+		F: FnOnce(&GenericArray<f32, Size<OUT>>) -> (GenericArray<f32, Size<OUT>>, f32),
+	{
+		let inputs = to_array(inputs);
+		let (weighted_inputs, output) = self.step(&inputs);
+		let (error, loss) = objective(&to_generic(&output));
+		let pre_error = self.backward(&inputs, &weighted_inputs, &output, to_array(&error));
+		(to_generic(&pre_error), loss)
+	}
+
+	fn _accumulate_batch<const B: usize, F>(
+		&mut self,
+		inputs: &[GenericArray<f32, Size<IN>>; B],
+		objective: F,
+	) -> ([GenericArray<f32, Size<IN>>; B], f32)
+	where
+		F: FnOnce(&[GenericArray<f32, Size<OUT>>; B]) -> ([GenericArray<f32, Size<OUT>>; B], f32),
+	{
+		let inputs = inputs.each_ref().map(to_array);
+		let steps = inputs.each_ref().map(|inputs| self.step(inputs));
+		let (errors, loss) = objective(&steps.each_ref().map(|(_, output)| to_generic(output)));
+		let pre_errors = map_batch(errors, |i, error| {
+			let (weighted_inputs, output) = &steps[i];
+			to_generic(&self.backward(&inputs[i], weighted_inputs, output, to_array(&error)))
+		});
+		(pre_errors, loss)
+	}
+
+	fn _apply_deltas(&mut self, batch_size: usize, speed: f32) {
+		self.apply_own_deltas(batch_size, speed)
+	}
+
+	fn _params_mut<F: FnMut(&mut f32, &mut f32)>(&mut self, f: &mut F) {
+		params_mut(&mut self.weights, &mut self.weight_deltas, f);
+		params_mut(&mut self.bias, &mut self.bias_deltas, f);
+	}
+
+	fn _penalty(&self) -> f32 { self.regularizer.reported_penalty(&self.weights) }
+}
+
+impl<Top: sealed::Sealed, Next: sealed::Sealed> sealed::Sealed for Stack<Top, Next> {}
+
+impl<
+		A: LayerActivation,
+		const IN: usize,
+		const OUT: usize,
+		L: Loss,
+		O: Optimizer,
+		FinalOut: AL,
+		Next: Differentiable<Size<OUT>, FinalOut>,
+	> Differentiable<Size<IN>, FinalOut> for Stack<Dense<A, IN, OUT, L, O>, Next>
+where
+	Const<IN>: ToUInt,
+	Const<OUT>: ToUInt,
+{
+	fn _calculate(&self, inputs: &GenericArray<f32, Size<IN>>) -> GenericArray<f32, FinalOut> {
+		self.next
+			._calculate(&to_generic(&self.top.step(&to_array(inputs)).1))
+	}
+
+	fn _loss(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> f32 {
+		Next::_loss(output, correct_output)
+	}
+
+	fn _error(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> (GenericArray<f32, FinalOut>, f32) {
+		Next::_error(output, correct_output)
+	}
+
+	fn _accumulate_with<F>(
+		&mut self,
+		inputs: &GenericArray<f32, Size<IN>>,
+		objective: F,
+	) -> (GenericArray<f32, Size<IN>>, f32)
+	where
+		F: FnOnce(&GenericArray<f32, FinalOut>) -> (GenericArray<f32, FinalOut>, f32),
+	{
+		let inputs = to_array(inputs);
+		let (weighted_inputs, output) = self.top.step(&inputs);
+		let (error, loss) = self.next._accumulate_with(&to_generic(&output), objective);
+		let pre_error = self
+			.top
+			.backward(&inputs, &weighted_inputs, &output, to_array(&error));
+		(to_generic(&pre_error), loss)
+	}
+
+	fn _accumulate_batch<const B: usize, F>(
+		&mut self,
+		inputs: &[GenericArray<f32, Size<IN>>; B],
+		objective: F,
+	) -> ([GenericArray<f32, Size<IN>>; B], f32)
+	where
+		F: FnOnce(&[GenericArray<f32, FinalOut>; B]) -> ([GenericArray<f32, FinalOut>; B], f32),
+	{
+		let inputs = inputs.each_ref().map(to_array);
+		let steps = inputs.each_ref().map(|inputs| self.top.step(inputs));
+		let outputs = steps.each_ref().map(|(_, output)| to_generic(output));
+		let (errors, loss) = self.next._accumulate_batch(&outputs, objective);
+		let pre_errors = map_batch(errors, |i, error| {
+			let (weighted_inputs, output) = &steps[i];
+			to_generic(
+				&self
+					.top
+					.backward(&inputs[i], weighted_inputs, output, to_array(&error)),
+			)
+		});
+		(pre_errors, loss)
+	}
+
+	fn _apply_deltas(&mut self, batch_size: usize, speed: f32) {
+		self.top.apply_own_deltas(batch_size, speed);
+		self.next._apply_deltas(batch_size, speed);
+	}
+
+	fn _params_mut<F: FnMut(&mut f32, &mut f32)>(&mut self, f: &mut F) {
+		self.top._params_mut(f);
+		self.next._params_mut(f);
+	}
+
+	fn _penalty(&self) -> f32 { self.top._penalty() + self.next._penalty() }
+}
+
+/// A GenericArray based layer did not have the size of the [Dense] layer it was converted to.
+///
+/// The conversions work for layers of any size, but only layers with up to 1024 inputs and neurons
+/// can be used as a [Network](crate::layers::Network) afterwards, see [ToUInt].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShapeMismatch {
+	/// inputs and neurons of the Dense layer
+	pub expected: (usize, usize),
+	/// inputs and neurons of the converted layer
+	pub found: (usize, usize),
+}
+
+impl<
+		A: LayerActivation,
+		Neurons: NL<Input>,
+		Input: AL,
+		L: Loss,
+		O: Optimizer,
+		const IN: usize,
+		const OUT: usize,
+	> TryFrom<OutputLayer<A, Neurons, Input, L, O>> for Dense<A, IN, OUT, L, O>
+{
+	type Error = ShapeMismatch;
+
+	fn try_from(layer: OutputLayer<A, Neurons, Input, L, O>) -> Result<Self, ShapeMismatch> {
+		Self::copy_layer(layer)
+	}
+}
+
+impl<A: LayerActivation, const IN: usize, const OUT: usize, L: Loss, O: Optimizer>
+	Dense<A, IN, OUT, L, O>
+{
+	/// copies the weights of layer, whose loss only matters if it is the final layer
+	fn copy_layer<Neurons: NL<Input>, Input: AL, LayerLoss: Loss>(
+		layer: OutputLayer<A, Neurons, Input, LayerLoss, O>,
+	) -> Result<Self, ShapeMismatch> {
+		if (Input::to_usize(), Neurons::to_usize()) != (IN, OUT) {
+			return Err(ShapeMismatch {
+				expected: (IN, OUT),
+				found: (Input::to_usize(), Neurons::to_usize()),
+			});
+		}
+		let mut out = Self::zeroed();
+		for (neuron, weights) in out.weights.iter_mut().zip(&layer.weights) {
+			neuron.copy_from_slice(weights);
+		}
+		out.bias.copy_from_slice(&layer.bias);
+		out.regularizer = layer.regularizer;
+		Ok(out)
+	}
+}
+
+impl<
+		A: LayerActivation,
+		Neurons: NL<Input>,
+		Input: AL,
+		FinalOut: AL,
+		Next: Differentiable<Neurons, FinalOut>,
+		L: Loss,
+		O: Optimizer,
+		const IN: usize,
+		const OUT: usize,
+		DenseNext: TryFrom<Next, Error = ShapeMismatch>,
+	> TryFrom<InnerLayer<A, Neurons, Input, FinalOut, Next, O>>
+	for Stack<Dense<A, IN, OUT, L, O>, DenseNext>
+{
+	type Error = ShapeMismatch;
+
+	fn try_from(
+		layer: InnerLayer<A, Neurons, Input, FinalOut, Next, O>,
+	) -> Result<Self, ShapeMismatch> {
+		Ok(Stack {
+			top: Dense::copy_layer(layer.inner)?,
+			next: DenseNext::try_from(layer.next)?,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		activation::{Identity, Sigmoid},
+		gradcheck::check,
+		layers::Network,
+		loss::BinaryCrossEntropy,
+		optimizer::Adam,
+		testing::{assert_batch_matches, sample},
+	};
+	use generic_array::{
+		arr,
+		typenum::{U2, U3, U4},
+	};
+
+	const XOR: [([f32; 2], [f32; 1]); 4] = [
+		([0., 0.], [0.]),
+		([0., 1.], [1.]),
+		([1., 0.], [1.]),
+		([1., 1.], [0.]),
+	];
+
+	#[test]
+	fn learns_xor() {
+		let mut rng = default_rng();
+		let mut network = Dense::<Sigmoid, 2, 3, Mse, Adam>::new_with_rng(&mut rng)
+			.push(Dense::<Sigmoid, 3, 1, Mse, Adam>::new_with_rng(&mut rng));
+		let xor = XOR.map(|(input, output)| (to_generic(&input), to_generic(&output)));
+		let before = network.teach_epoch(xor, 4, 0.);
+		for _ in 0..2000 {
+			network.teach_epoch(xor, 1, 0.05);
+		}
+		let after = network.teach_epoch(xor, 4, 0.);
+		assert!(after < before / 10., "{} -> {}", before, after);
+	}
+
+	#[test]
+	fn converts_generic_array_networks() {
+		type Generic = InnerLayer<Sigmoid, U3, U2, U2, OutputLayer<Sigmoid, U2, U3>>;
+		type Const = Stack<Dense<Sigmoid, 2, 3>, Dense<Sigmoid, 3, 2>>;
+
+		let mut generic = Generic::push(OutputLayer::new());
+		generic.backprop(&arr![f32; 0.3, 0.8], &arr![f32; 1., 0.], 0.5);
+		let converted = Const::try_from(generic.clone()).unwrap();
+		let input = arr![f32; 0.9, -0.2];
+		assert_eq!(generic.calculate(&input), converted.calculate(&input));
+
+		// the loss of the top layer plays no part, so it can be anything
+		type Entropy = Stack<Dense<Sigmoid, 2, 3, BinaryCrossEntropy>, Dense<Sigmoid, 3, 2>>;
+		let converted = Entropy::try_from(generic.clone()).unwrap();
+		assert_eq!(generic.calculate(&input), converted.calculate(&input));
+
+		let wrong = Stack::<Dense<Sigmoid, 2, 4>, Dense<Sigmoid, 4, 2>>::try_from(generic);
+		assert_eq!(wrong.unwrap_err(), ShapeMismatch {
+			expected: (2, 4),
+			found: (2, 3)
+		});
+	}
+
+	#[test]
+	fn reads_serialized_generic_array_layers() {
+		let layer = OutputLayer::<Sigmoid, U3, U2>::new();
+		let bytes = bincode::serialize(&layer).unwrap();
+		let dense: Dense<Sigmoid, 2, 3> = bincode::deserialize(&bytes).unwrap();
+		let input = arr![f32; 0.5, 1.];
+		assert_eq!(layer.calculate(&input), dense.calculate(&input));
+
+		// more than serde supports for plain arrays
+		let big = Dense::<Sigmoid, 40, 33, Mse, Adam>::new();
+		let bytes = bincode::serialize(&big).unwrap();
+		let restored: Dense<Sigmoid, 40, 33, Mse, Adam> = bincode::deserialize(&bytes).unwrap();
+		assert_eq!(format!("{:?}", restored), format!("{:?}", big));
+	}
+
+	#[test]
+	fn gradients() {
+		let network = Dense::<Identity, 2, 3>::new().push(Dense::<Sigmoid, 3, 4>::new());
+		let result = check(&network, &sample::<U2>(1), &sample(2), 1e-2, 1e-2);
+		assert!(result.is_ok(), "{:?}", result);
+
+		// GenericArray based layers on top of const generic ones
+		let mixed = InnerLayer::<Sigmoid, U2, U3, U4, _>::push(network);
+		let result = check(&mixed, &sample::<U3>(3), &sample(4), 1e-2, 1e-2);
+		assert!(result.is_ok(), "{:?}", result);
+	}
+
+	#[test]
+	fn batches() {
+		let network = Dense::<Identity, 2, 3>::new().push(Dense::<Sigmoid, 3, 4>::new());
+		let inputs = [sample::<U2>(1), sample(2), sample(3)];
+		assert_batch_matches(&network, &inputs, &[sample::<U4>(4), sample(5), sample(6)]);
+
+		let mixed = InnerLayer::<Sigmoid, U2, U3, U4, _>::push(network);
+		let inputs = [sample::<U3>(7), sample(8), sample(9)];
+		assert_batch_matches(&mixed, &inputs, &[sample::<U4>(10), sample(11), sample(12)]);
+	}
+}
@@ -0,0 +1,332 @@
+//! Layers splitting a convolution into a spatial and a channel mixing part, like the blocks of
+//! MobileNet.
+//!
+//! A [ConvolutionalLayer](crate::convolution::ConvolutionalLayer) with N filters of CH * CW
+//! needs `CH * CW * D * N` multiplications for each position of the output. A [DepthwiseConv2d]
+//! followed by a [PointwiseConv2d] only needs `CH * CW * D + D * N` for about the same effect,
+//! which keeps vision models small enough for microcontrollers.
+//!
+//! ```
+//! use dumbnet::{
+//!     activation::{ReLu, Sigmoid},
+//!     convolution::Same,
+//!     depthwise::{DepthwiseConv2d, PointwiseConv2d},
+//!     layers::OutputLayer,
+//! };
+//! use generic_array::typenum::{U1, U128, U3, U4, U8};
+//!
+//! // a 3x3 filter per channel keeps the 4x4x4 input, then 8 1x1 filters mix the channels
+//! type Head = OutputLayer<Sigmoid, U1, U128>;
+//! type Pointwise = PointwiseConv2d<ReLu, U4, U4, U4, U8, U1, Head>;
+//! type Depthwise =
+//!     DepthwiseConv2d<ReLu, U4, U4, U4, U3, U3, U1, U1, Same<U3, U3>, U1, U1, Pointwise>;
+//! let block = Depthwise::push(Pointwise::push(Head::new()));
+//! ```
+//!
+//! for generic layer documentation see [layers](crate::layers)
+use core::{
+	marker::PhantomData,
+	ops::{Add, Div, Mul, Sub},
+};
+use generic_array::typenum::{bit::B1, Diff, Prod, Quot, Sub1, Unsigned, U2};
+
+use crate::{
+	convolution::{
+		Connections, ConvHSize, ConvOutputSize, ConvWSize, Convolution, DilatedSize, PaddedSize,
+		PaddingFor,
+	},
+	layers::{AL, NL},
+	optimizer::Sgd,
+};
+
+/// Convolves each of the D channels of its input with its own CH * CW filter, so channel d of the
+/// output only depends on channel d of the input.
+///
+/// Padding, strides and dilation work like in a
+/// [ConvolutionalLayer](crate::convolution::ConvolutionalLayer) with D filters, and so does the
+/// layout of the output.
+pub type DepthwiseConv2d<A, W, H, D, CW, CH, SH, SW, P, DL, FinalOut, Next, O = Sgd> =
+	Convolution<A, Depthwise<W, H, D, CW, CH, SH, SW, P, DL>, FinalOut, Next, O>;
+
+/// The connections of a [DepthwiseConv2d], one CH * CW filter per channel.
+#[derive(Debug, Clone)]
+pub struct Depthwise<W, H, D, CW, CH, SH, SW, P, DL>(PhantomData<(W, H, D, CW, CH, SH, SW, P, DL)>);
+
+impl<
+		W: AL,
+		H: AL,
+		D: NL<Prod<CH, CW>>,
+		CW: AL,
+		CH: AL,
+		SH: AL,
+		SW: AL,
+		P: PaddingFor<H, W>,
+		DL: AL,
+	> Connections for Depthwise<W, H, D, CW, CH, SH, SW, P, DL>
+where
+	CH: Mul<CW>,
+	Prod<CH, CW>: AL,
+
+	H: Mul<W>,
+	Prod<H, W>: Mul<D>,
+	Prod<Prod<H, W>, D>: AL,
+
+	CH: Sub<B1>,
+	Sub1<CH>: Mul<DL>,
+	Prod<Sub1<CH>, DL>: Add<B1>,
+	P::Height: Mul<U2>,
+	H: Add<Prod<P::Height, U2>>,
+	PaddedSize<H, P::Height>: Sub<DilatedSize<CH, DL>>,
+	Diff<PaddedSize<H, P::Height>, DilatedSize<CH, DL>>: Div<SH>,
+	Quot<Diff<PaddedSize<H, P::Height>, DilatedSize<CH, DL>>, SH>: Add<B1>,
+
+	CW: Sub<B1>,
+	Sub1<CW>: Mul<DL>,
+	Prod<Sub1<CW>, DL>: Add<B1>,
+	P::Width: Mul<U2>,
+	W: Add<Prod<P::Width, U2>>,
+	PaddedSize<W, P::Width>: Sub<DilatedSize<CW, DL>>,
+	Diff<PaddedSize<W, P::Width>, DilatedSize<CW, DL>>: Div<SW>,
+	Quot<Diff<PaddedSize<W, P::Width>, DilatedSize<CW, DL>>, SW>: Add<B1>,
+
+	ConvHSize<H, CH, SH, P::Height, DL>: Mul<ConvWSize<W, CW, SW, P::Width, DL>>,
+	ConvHSize<H, CH, SH, P::Height, DL>: AL,
+	ConvWSize<W, CW, SW, P::Width, DL>: AL,
+	Prod<ConvHSize<H, CH, SH, P::Height, DL>, ConvWSize<W, CW, SW, P::Width, DL>>: Mul<D>,
+
+	ConvOutputSize<W, H, CW, CH, D, SH, SW, P, DL>: AL,
+{
+	type Input = Prod<Prod<H, W>, D>;
+	type Output = ConvOutputSize<W, H, CW, CH, D, SH, SW, P, DL>;
+	type Weights = Prod<CH, CW>;
+	type Filters = D;
+
+	#[inline(always)]
+	fn for_each_connection<F: FnMut(usize, usize, usize, usize)>(mut f: F) {
+		let width = W::to_usize();
+		let height = H::to_usize();
+		let filter_width = CW::to_usize();
+		let filter_height = CH::to_usize();
+		let stride_height = SH::to_usize();
+		let stride_width = SW::to_usize();
+		let padding_height = P::Height::to_usize() as isize;
+		let padding_width = P::Width::to_usize() as isize;
+		let dilation = DL::to_usize();
+		let output_height = ConvHSize::<H, CH, SH, P::Height, DL>::to_usize();
+		let output_width = ConvWSize::<W, CW, SW, P::Width, DL>::to_usize();
+
+		// unlike a full convolution filter d only ever sees channel d
+		for d in 0..D::to_usize() {
+			for out_y in 0..output_height {
+				for out_x in 0..output_width {
+					let output = (d * output_height + out_y) * output_width + out_x;
+					for filter_y in 0..filter_height {
+						let padded_y = (out_y * stride_height + filter_y * dilation) as isize;
+						let y = match P::source(padded_y - padding_height, height) {
+							Some(y) => y,
+							None => continue,
+						};
+						for filter_x in 0..filter_width {
+							let padded_x = (out_x * stride_width + filter_x * dilation) as isize;
+							let x = match P::source(padded_x - padding_width, width) {
+								Some(x) => x,
+								None => continue,
+							};
+							let input = (d * height + y) * width + x;
+							f(output, d, filter_y * filter_width + filter_x, input);
+						}
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Mixes the D channels of its input into N channels with a 1x1 convolution, each position of
+/// the output being a weighted sum of the channels at the same position of the input.
+///
+/// Equal to a [ConvolutionalLayer](crate::convolution::ConvolutionalLayer) with N 1x1 filters,
+/// just without the type level bookkeeping for filter sizes.
+pub type PointwiseConv2d<A, W, H, D, N, FinalOut, Next, O = Sgd> =
+	Convolution<A, Pointwise<W, H, D, N>, FinalOut, Next, O>;
+
+/// The connections of a [PointwiseConv2d], one weight per input channel for each output channel.
+#[derive(Debug, Clone)]
+pub struct Pointwise<W, H, D, N>(PhantomData<(W, H, D, N)>);
+
+impl<W: AL, H: AL, D: AL, N: NL<D>> Connections for Pointwise<W, H, D, N>
+where
+	H: Mul<W>,
+	Prod<H, W>: Mul<D>,
+	Prod<H, W>: Mul<N>,
+	Prod<H, W>: AL,
+	Prod<Prod<H, W>, D>: AL,
+	Prod<Prod<H, W>, N>: AL,
+{
+	type Input = Prod<Prod<H, W>, D>;
+	type Output = Prod<Prod<H, W>, N>;
+	type Weights = D;
+	type Filters = N;
+
+	#[inline(always)]
+	fn for_each_connection<F: FnMut(usize, usize, usize, usize)>(mut f: F) {
+		let plane = Prod::<H, W>::to_usize();
+		for n in 0..N::to_usize() {
+			for position in 0..plane {
+				for d in 0..D::to_usize() {
+					f(n * plane + position, n, d, d * plane + position);
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		activation::Sigmoid,
+		convolution::{ConvolutionalLayer, Reflect, Valid, Zeros},
+		gradcheck::check,
+		layers::{Differentiable, Network, OutputLayer},
+		testing::{params, sample},
+	};
+	use generic_array::typenum::{U1, U16, U18, U2, U24, U3, U30, U32, U4, U40, U5};
+
+	type DepthwiseLayer = DepthwiseConv2d<
+		Sigmoid,
+		U5,
+		U4,
+		U2,
+		U3,
+		U2,
+		U1,
+		U2,
+		Zeros<U1, U1>,
+		U1,
+		U2,
+		OutputLayer<Sigmoid, U2, U30>,
+	>;
+
+	#[test]
+	fn matches_a_convolution_with_one_channel_per_filter() {
+		// the same layer as a full convolution, with the weights for the other channels at 0
+		type Full = ConvolutionalLayer<
+			Sigmoid,
+			U5,
+			U4,
+			U2,
+			U3,
+			U2,
+			U2,
+			U1,
+			U2,
+			Zeros<U1, U1>,
+			U1,
+			U2,
+			OutputLayer<Sigmoid, U2, U30>,
+		>;
+		let mut depthwise = DepthwiseLayer::push(OutputLayer::new());
+		let mut full = Full::push(OutputLayer::new());
+		let filter = 3 * 2;
+		let mut copied = params(&mut depthwise).into_iter();
+		let mut index = 0;
+		full._params_mut(&mut |param, _| {
+			let (filter_index, depth) = (index / (2 * filter), index / filter % 2);
+			*param = if index >= 2 * 2 * filter || filter_index == depth {
+				copied.next().unwrap().0
+			} else {
+				0.
+			};
+			index += 1;
+		});
+
+		let input = sample::<U40>(1);
+		let correct = sample(2);
+		assert_eq!(depthwise.calculate(&input), full.calculate(&input));
+		assert_eq!(
+			depthwise.accumulate(&input, &correct),
+			full.accumulate(&input, &correct)
+		);
+		let full_params = params(&mut full);
+		let shared = full_params
+			.iter()
+			.enumerate()
+			.filter(|(i, _)| *i >= 2 * 2 * filter || i / (2 * filter) == i / filter % 2)
+			.map(|(_, p)| *p);
+		assert!(params(&mut depthwise).into_iter().eq(shared));
+	}
+
+	#[test]
+	fn matches_a_1x1_convolution() {
+		type Pointwise =
+			PointwiseConv2d<Sigmoid, U3, U2, U4, U3, U2, OutputLayer<Sigmoid, U2, U18>>;
+		type Full = ConvolutionalLayer<
+			Sigmoid,
+			U3,
+			U2,
+			U4,
+			U1,
+			U1,
+			U3,
+			U1,
+			U1,
+			Valid,
+			U1,
+			U2,
+			OutputLayer<Sigmoid, U2, U18>,
+		>;
+		let mut pointwise = Pointwise::push(OutputLayer::new());
+		let mut full = Full::push(OutputLayer::new());
+		let mut copied = params(&mut pointwise).into_iter();
+		full._params_mut(&mut |param, _| *param = copied.next().unwrap().0);
+
+		let input = sample::<U24>(3);
+		let correct = sample(4);
+		assert_eq!(pointwise.calculate(&input), full.calculate(&input));
+		assert_eq!(
+			pointwise.accumulate(&input, &correct),
+			full.accumulate(&input, &correct)
+		);
+		assert_eq!(params(&mut pointwise), params(&mut full));
+	}
+
+	#[test]
+	fn gradients() {
+		let depthwise = DepthwiseLayer::push(OutputLayer::new());
+		let result = check(&depthwise, &sample::<U40>(5), &sample(6), 1e-2, 1e-2);
+		assert!(result.is_ok(), "{:?}", result);
+
+		let pointwise =
+			PointwiseConv2d::<Sigmoid, U3, U2, U4, U3, U2, _>::push(
+				OutputLayer::<Sigmoid, U2, U18>::new(),
+			);
+		let result = check(&pointwise, &sample::<U24>(7), &sample(8), 1e-2, 1e-2);
+		assert!(result.is_ok(), "{:?}", result);
+	}
+
+	#[test]
+	fn serializes_a_block() {
+		type Block = DepthwiseConv2d<
+			Sigmoid,
+			U4,
+			U4,
+			U2,
+			U3,
+			U3,
+			U1,
+			U1,
+			Reflect<U1, U1>,
+			U1,
+			U2,
+			PointwiseConv2d<Sigmoid, U4, U4, U2, U1, U2, OutputLayer<Sigmoid, U2, U16>>,
+		>;
+		let mut block = Block::push(PointwiseConv2d::push(OutputLayer::new()));
+		let input = sample::<U32>(9);
+		block.backprop(&input, &sample(10), 0.5);
+
+		let bytes = bincode::serialize(&block).unwrap();
+		let restored: Block = bincode::deserialize(&bytes).unwrap();
+		assert_eq!(block.calculate(&input), restored.calculate(&input));
+	}
+}
@@ -0,0 +1,246 @@
+//! Dropout randomly switches off some of the outputs of the layer above it while training.
+//!
+//! That keeps neurons from relying on a few specific others, which makes small networks overfit
+//! less. Only [accumulate](crate::layers::Network::accumulate) and everything built on top of it,
+//! like [backprop](crate::layers::Network::backprop), drop anything.
+//! [calculate](crate::layers::Network::calculate) and
+//! [evaluate](crate::layers::Network::evaluate) pass everything through, so there is no mode to
+//! switch between training and inference.
+use crate::layers::{default_rng, map_batch, sealed, Differentiable, AL};
+use core::fmt::Debug;
+use generic_array::{typenum::Unsigned, GenericArray};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+
+/// Drops each of its N inputs with a probability of P percent while training and scales the
+/// rest up, so their sum stays the same on average.
+///
+/// Push it on top of the layer that should get the dropped inputs, then push the layer producing
+/// them on top of the Dropout.
+///
+/// The rng is not serialized, a deserialized Dropout starts over from [default_rng].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "N: AL, FinalOut: AL")]
+pub struct Dropout<
+	P: Unsigned + Debug + Clone,
+	N: AL,
+	FinalOut: AL,
+	Next: Differentiable<N, FinalOut>,
+> {
+	next: Next,
+	#[serde(skip, default = "default_rng")]
+	rng: Pcg32,
+	phantom: core::marker::PhantomData<(P, N, FinalOut)>,
+}
+
+impl<P: Unsigned + Debug + Clone, N: AL, FinalOut: AL, Next: Differentiable<N, FinalOut>>
+	Dropout<P, N, FinalOut, Next>
+{
+	/// Pushes this layer on top of an existing layer.
+	pub fn push(next: Next) -> Self {
+		Self {
+			next,
+			rng: default_rng(),
+			phantom: core::marker::PhantomData,
+		}
+	}
+
+	/// like [push](Dropout::push), seeding the rng that picks the dropped inputs from rng
+	pub fn push_with_rng<R: Rng>(next: Next, rng: &mut R) -> Self {
+		Self {
+			rng: Pcg32::from_rng(rng).expect("could not seed from rng"),
+			..Self::push(next)
+		}
+	}
+
+	/// the probability of an input getting dropped
+	pub fn probability() -> f32 { P::to_usize() as f32 / 100. }
+
+	/// draws which inputs to drop, 0 for dropped ones and the scale of the rest otherwise
+	fn mask(&mut self) -> GenericArray<f32, N> {
+		let probability = Self::probability();
+		let scale = if probability < 1. {
+			1. / (1. - probability)
+		} else {
+			0.
+		};
+		let rng = &mut self.rng;
+		(0..N::to_usize())
+			.map(|_| {
+				if rng.gen::<f32>() < probability {
+					0.
+				} else {
+					scale
+				}
+			})
+			.collect()
+	}
+}
+
+impl<P: Unsigned + Debug + Clone, N: AL, FinalOut: AL, Next: Differentiable<N, FinalOut>>
+	sealed::Sealed for Dropout<P, N, FinalOut, Next>
+{
+}
+
+impl<P: Unsigned + Debug + Clone, N: AL, FinalOut: AL, Next: Differentiable<N, FinalOut>>
+	Differentiable<N, FinalOut> for Dropout<P, N, FinalOut, Next>
+{
+	fn _calculate(&self, inputs: &GenericArray<f32, N>) -> GenericArray<f32, FinalOut> {
+		self.next._calculate(inputs)
+	}
+
+	fn _loss(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> f32 {
+		Next::_loss(output, correct_output)
+	}
+
+	fn _error(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> (GenericArray<f32, FinalOut>, f32) {
+		Next::_error(output, correct_output)
+	}
+
+	fn _accumulate_with<F>(
+		&mut self,
+		inputs: &GenericArray<f32, N>,
+		objective: F,
+	) -> (GenericArray<f32, N>, f32)
+	where
+		F: FnOnce(&GenericArray<f32, FinalOut>) -> (GenericArray<f32, FinalOut>, f32),
+	{
+		// the mask lives on the stack until the error comes back from the lower layers
+		let mask = self.mask();
+		let dropped: GenericArray<f32, N> = inputs.iter().zip(&mask).map(|(i, m)| i * m).collect();
+		let (mut error, loss) = self.next._accumulate_with(&dropped, objective);
+		error.iter_mut().zip(&mask).for_each(|(e, m)| *e *= m);
+		(error, loss)
+	}
+
+	fn _accumulate_batch<const B: usize, F>(
+		&mut self,
+		inputs: &[GenericArray<f32, N>; B],
+		objective: F,
+	) -> ([GenericArray<f32, N>; B], f32)
+	where
+		F: FnOnce(&[GenericArray<f32, FinalOut>; B]) -> ([GenericArray<f32, FinalOut>; B], f32),
+	{
+		let masks = inputs.each_ref().map(|_| self.mask());
+		let dropped = map_batch(masks.clone(), |i, mask| {
+			inputs[i].iter().zip(&mask).map(|(i, m)| i * m).collect()
+		});
+		let (errors, loss) = self.next._accumulate_batch(&dropped, objective);
+		let errors = map_batch(errors, |i, mut error: GenericArray<f32, N>| {
+			error.iter_mut().zip(&masks[i]).for_each(|(e, m)| *e *= m);
+			error
+		});
+		(errors, loss)
+	}
+
+	fn _apply_deltas(&mut self, batch_size: usize, speed: f32) {
+		self.next._apply_deltas(batch_size, speed);
+	}
+
+	fn _params_mut<F: FnMut(&mut f32, &mut f32)>(&mut self, f: &mut F) { self.next._params_mut(f); }
+
+	fn _penalty(&self) -> f32 { self.next._penalty() }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		activation::Sigmoid,
+		gradcheck::check,
+		layers::{InnerLayer, Network, OutputLayer},
+		loss::Mse,
+		testing::assert_batch_matches,
+	};
+	use generic_array::{
+		arr,
+		typenum::{U0, U2, U50, U8},
+	};
+
+	type Dropped = Dropout<U50, U8, U2, OutputLayer<Sigmoid, U2, U8>>;
+
+	fn input() -> GenericArray<f32, U8> { arr![f32; 0.1, 0.9, -0.5, 0.3, 0.7, -0.2, 0.4, 1.] }
+
+	#[test]
+	fn calculates_without_dropping() {
+		let dropout = Dropped::push(OutputLayer::new());
+		assert_eq!(
+			dropout.calculate(&input()),
+			dropout.next.calculate(&input())
+		);
+	}
+
+	#[test]
+	fn drops_while_training() {
+		let correct = arr![f32; 1., 0.];
+		let mut dropped = 0;
+		let mut dropout = Dropped::push(OutputLayer::new());
+		for _ in 0..20 {
+			let mut plain = dropout.next.clone();
+			let (error, _) = dropout.accumulate(&input(), &correct);
+			let (plain_error, _) = plain.accumulate(&input(), &correct);
+			// dropped inputs pass no error back and get no deltas, the rest the scaled up ones
+			for i in 0..8 {
+				let deltas = dropout.next.weight_deltas.iter().map(|n| n[i]);
+				if error[i] == 0. {
+					dropped += 1;
+					assert!(deltas.clone().all(|d| d == 0.));
+				} else {
+					assert!(deltas.clone().all(|d| d != 0.));
+					assert_ne!(error[i], plain_error[i]);
+				}
+			}
+			dropout.apply_batch(1, 0.);
+		}
+		assert!(dropped > 50 && dropped < 110, "{}", dropped);
+
+		let seeded = |seed| {
+			let mut rng = Pcg32::seed_from_u64(seed);
+			let mut dropout = Dropped::push_with_rng(OutputLayer::new(), &mut rng);
+			dropout.accumulate(&input(), &correct).0
+		};
+		assert_eq!(seeded(1), seeded(1));
+		assert_ne!(seeded(1), seeded(2));
+	}
+
+	#[test]
+	fn trains_in_a_stack() {
+		let mut network = InnerLayer::<
+			Sigmoid,
+			U8,
+			U2,
+			U2,
+			Dropout<U50, U8, U2, OutputLayer<Sigmoid, U2, U8, Mse>>,
+		>::push(Dropout::push(OutputLayer::new()));
+		let input = arr![f32; 0.2, 0.8];
+		let correct = arr![f32; 1., 0.];
+		let before = network.evaluate(&input, &correct);
+		for _ in 0..200 {
+			network.backprop(&input, &correct, 0.5);
+		}
+		assert!(network.evaluate(&input, &correct) < before / 2.);
+	}
+
+	#[test]
+	fn gradients() {
+		// without dropping anything the gradients are the ones of the layer below
+		let network = Dropout::<U0, U8, U2, _>::push(OutputLayer::<Sigmoid, U2, U8>::new());
+		let result = check(&network, &input(), &arr![f32; 0., 1.], 1e-2, 1e-2);
+		assert!(result.is_ok(), "{:?}", result);
+	}
+
+	#[test]
+	fn batches() {
+		// every example of the batch gets its own mask
+		let network = Dropout::<U50, U8, U2, _>::push(OutputLayer::<Sigmoid, U2, U8>::new());
+		let inputs = [input(), input(), input()];
+		let outputs = [arr![f32; 0., 1.], arr![f32; 1., 0.], arr![f32; 1., 1.]];
+		assert_batch_matches(&network, &inputs, &outputs);
+	}
+}
@@ -0,0 +1,307 @@
+//! Checks the gradients a [Network] calculates during training against numeric ones.
+//!
+//! Every parameter and every input gets nudged up and down by a tiny epsilon, the change of the
+//! loss divided by the size of the nudge approximates its gradient. If that disagrees with what
+//! [accumulate](Network::accumulate) calculated, something in the backpropagation of the layer is
+//! off.
+//!
+//! This runs the network twice for every single parameter, so only use it on small layers.
+//!
+//! Keep in mind that activations like [ReLu](crate::activation::ReLu) deliberately use a slightly
+//! adapted derivate, which shows up as a mismatch.
+use crate::layers::{Network, AL};
+use generic_array::GenericArray;
+
+/// A gradient that differs from the numeric one by more than the tolerance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mismatch {
+	/// the gradient of the parameter at index, in the order of
+	/// [_params_mut](crate::layers::Differentiable::_params_mut)
+	Parameter {
+		index: usize,
+		analytic: f32,
+		numeric: f32,
+	},
+	/// the gradient of the input at index
+	Input {
+		index: usize,
+		analytic: f32,
+		numeric: f32,
+	},
+}
+
+/// relative error between 0 and 1
+///
+/// Gradients whose magnitudes add up to less than 1e-3 are compared absolutely against that
+/// floor instead, below it f32 differences are mostly rounding noise.
+fn error(analytic: f32, numeric: f32) -> f32 {
+	(analytic - numeric).abs() / (analytic.abs() + numeric.abs()).max(1e-3)
+}
+
+/// calls f with the parameter at index and its delta
+fn with_param<M, P, F>(model: &mut M, params: &mut P, index: usize, mut f: F)
+where
+	P: FnMut(&mut M, &mut dyn FnMut(&mut f32, &mut f32)),
+	F: FnMut(&mut f32, &mut f32),
+{
+	let mut i = 0;
+	params(model, &mut |param, delta| {
+		if i == index {
+			f(param, delta);
+		}
+		i += 1;
+	});
+}
+
+/// Compares the gradients of the loss with respect to every parameter and input of network
+/// against numeric ones, calculated by nudging each of them by epsilon.
+///
+/// Returns the biggest error found, or the first gradient whose error is above tolerance.
+/// epsilon 1e-2 and tolerance 1e-2 work well for f32.
+pub fn check<Input, FinalOut, Net>(
+	network: &Net,
+	input: &GenericArray<f32, Input>,
+	correct_output: &GenericArray<f32, FinalOut>,
+	epsilon: f32,
+	tolerance: f32,
+) -> Result<f32, Mismatch>
+where
+	Input: AL,
+	FinalOut: AL,
+	Net: Network<Input, FinalOut>,
+{
+	let (mut worst, input_errors) = check_params(
+		network,
+		|network, f| network._params_mut(&mut |param, delta| f(param, delta)),
+		|network| network.accumulate(input, correct_output).0,
+		|network| network.evaluate(input, correct_output),
+		epsilon,
+		tolerance,
+	)?;
+
+	// errors point downhill, so they are the negated gradients
+	for index in 0..Input::to_usize() {
+		let gradient = -input_errors[index];
+
+		let mut nudged = input.clone();
+		nudged[index] += epsilon;
+		let up = network.evaluate(&nudged, correct_output);
+		nudged[index] = input[index] - epsilon;
+		let down = network.evaluate(&nudged, correct_output);
+
+		let numeric = (up - down) / (2. * epsilon);
+		let error = error(gradient, numeric);
+		if error > tolerance {
+			return Err(Mismatch::Input {
+				index,
+				analytic: gradient,
+				numeric,
+			});
+		}
+		worst = worst.max(error);
+	}
+
+	Ok(worst)
+}
+
+/// Compares the gradients of the loss with respect to the parameters of any model against
+/// numeric ones, for models that don't fit into [check].
+///
+/// params visits every parameter of the model with its delta, always in the same order.
+/// accumulate adds the negated gradients of one example to the deltas, loss calculates the loss
+/// of the same example. Returns the biggest error found together with whatever accumulate
+/// returned, or the first parameter whose error is above tolerance.
+pub fn check_params<M, R, P, A, L>(
+	model: &M,
+	mut params: P,
+	accumulate: A,
+	loss: L,
+	epsilon: f32,
+	tolerance: f32,
+) -> Result<(f32, R), Mismatch>
+where
+	M: Clone,
+	P: FnMut(&mut M, &mut dyn FnMut(&mut f32, &mut f32)),
+	A: FnOnce(&mut M) -> R,
+	L: Fn(&M) -> f32,
+{
+	let mut worst = 0f32;
+
+	// deltas point downhill, so they are the negated gradients
+	let mut analytic = model.clone();
+	let mut count = 0;
+	params(&mut analytic, &mut |_, delta| {
+		*delta = 0.;
+		count += 1;
+	});
+	let accumulated = accumulate(&mut analytic);
+
+	let mut probe = model.clone();
+	for index in 0..count {
+		let mut gradient = 0.;
+		with_param(&mut analytic, &mut params, index, |_, delta| {
+			gradient = -*delta
+		});
+
+		let mut original = 0.;
+		with_param(&mut probe, &mut params, index, |param, _| {
+			original = *param;
+			*param = original + epsilon;
+		});
+		let up = loss(&probe);
+		with_param(&mut probe, &mut params, index, |param, _| {
+			*param = original - epsilon
+		});
+		let down = loss(&probe);
+		with_param(&mut probe, &mut params, index, |param, _| *param = original);
+
+		let numeric = (up - down) / (2. * epsilon);
+		let error = error(gradient, numeric);
+		if error > tolerance {
+			return Err(Mismatch::Parameter {
+				index,
+				analytic: gradient,
+				numeric,
+			});
+		}
+		worst = worst.max(error);
+	}
+
+	Ok((worst, accumulated))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		activation::{self, Sigmoid},
+		convolution::{ConvolutionalLayer, Reflect, Valid},
+		layers::{InnerLayer, OutputLayer},
+		loss::{BinaryCrossEntropy, Loss, Mse},
+		softmax::SoftMax,
+		testing::sample,
+	};
+	use generic_array::typenum::{U1, U18, U2, U24, U3, U4, U5};
+	fn one_hot<L: AL>(index: usize) -> GenericArray<f32, L> {
+		let mut out = GenericArray::default();
+		out[index] = 1.;
+		out
+	}
+
+	fn assert_gradients<Input, FinalOut, Net>(network: &Net, correct: GenericArray<f32, FinalOut>)
+	where
+		Input: AL,
+		FinalOut: AL,
+		Net: Network<Input, FinalOut>,
+	{
+		let result = check(network, &sample(1), &correct, 1e-2, 1e-2);
+		assert!(result.is_ok(), "{:?}", result);
+	}
+
+	#[test]
+	fn output_layer() {
+		assert_gradients(&OutputLayer::<Sigmoid, U3, U4>::new(), one_hot(1));
+		assert_gradients(
+			&OutputLayer::<Sigmoid, U3, U4, BinaryCrossEntropy>::new(),
+			one_hot(2),
+		);
+	}
+
+	#[test]
+	fn inner_layers() {
+		type Stack = InnerLayer<
+			Sigmoid,
+			U4,
+			U3,
+			U2,
+			InnerLayer<Sigmoid, U5, U4, U2, OutputLayer<Sigmoid, U2, U5>>,
+		>;
+		let network = Stack::push(InnerLayer::push(OutputLayer::new()));
+		assert_gradients(&network, one_hot(0));
+	}
+
+	#[test]
+	fn softmax() {
+		assert_gradients(&SoftMax::<U3, U4>::new(), one_hot(1));
+		assert_gradients(&SoftMax::<U3, U4, Mse>::new(), one_hot(2));
+
+		let network = InnerLayer::<Sigmoid, U4, U2, _, _>::push(SoftMax::<U3, U4>::new());
+		assert_gradients(&network, one_hot(0));
+
+		// softmax in a hidden layer
+		let network =
+			InnerLayer::<activation::SoftMax, U4, U2, _, _>::push(
+				OutputLayer::<Sigmoid, U3, U4>::new(),
+			);
+		assert_gradients(&network, one_hot(0));
+	}
+
+	#[test]
+	fn convolution() {
+		let network = ConvolutionalLayer::<
+			Sigmoid,
+			U5,
+			U4,
+			U2,
+			U3,
+			U2,
+			U2,
+			U1,
+			U1,
+			Valid,
+			U1,
+			U1,
+			OutputLayer<Sigmoid, U1, U18>,
+		>::push(OutputLayer::new());
+		assert_gradients(&network, one_hot(0));
+
+		// padded on all sides, with a dilated filter moving 2 columns at a time
+		let network = ConvolutionalLayer::<
+			Sigmoid,
+			U5,
+			U4,
+			U2,
+			U3,
+			U2,
+			U2,
+			U1,
+			U2,
+			Reflect<U1, U2>,
+			U2,
+			U1,
+			OutputLayer<Sigmoid, U1, U24>,
+		>::push(OutputLayer::new());
+		assert_gradients(&network, one_hot(0));
+	}
+
+	/// claims the gradient is twice as steep as it is
+	#[derive(Debug, Serialize, Deserialize, Clone)]
+	enum Wrong {}
+
+	impl Loss for Wrong {
+		fn loss(prediction: &[f32], reality: &[f32]) -> f32 { Mse::loss(prediction, reality) }
+
+		fn gradient(prediction: &[f32], reality: &[f32], gradient: &mut [f32]) {
+			Mse::gradient(prediction, reality, gradient);
+			gradient.iter_mut().for_each(|g| *g *= 2.);
+		}
+	}
+
+	#[test]
+	fn small_gradients_are_compared_relatively() {
+		assert!(error(0.004, 0.008) > 1e-2);
+		assert!(error(0.004, 0.00401) < 1e-2);
+		assert_eq!(error(0., 0.), 0.);
+	}
+
+	#[test]
+	fn finds_mismatches() {
+		let layer = OutputLayer::<Sigmoid, U3, U4, Wrong>::new();
+		let result = check(&layer, &sample(2), &one_hot(1), 1e-2, 1e-2);
+		assert!(
+			matches!(result, Err(Mismatch::Parameter { .. })),
+			"{:?}",
+			result
+		);
+	}
+}
@@ -0,0 +1,279 @@
+//! Initializers decide the weights a [layer](crate::layers) starts out with.
+//!
+//! Weights that are too big saturate activations like [Sigmoid](crate::activation::Sigmoid),
+//! weights that are too small make the signal fade away in deep networks. Most initializers
+//! therefore scale the weights by the number of inputs (fan_in) and neurons (fan_out) of the
+//! layer.
+//!
+//! Just like [activations](crate::activation) the Enums in this module are empty and therefore
+//! not initializable. They are only used as type parameters.
+use crate::layers::{AL, NL};
+use core::f32::consts::PI;
+use generic_array::GenericArray;
+use rand::Rng;
+
+pub trait Initializer {
+	/// fills the weights of a layer
+	fn weights<W: Weights, R: Rng>(weights: &mut W, rng: &mut R);
+
+	/// biases start out as 0 unless the initializer says otherwise
+	fn bias<R: Rng>(bias: &mut [f32], _rng: &mut R) {
+		for bias in bias.iter_mut() {
+			*bias = 0.;
+		}
+	}
+}
+
+/// The weights of a layer, one row of weights per neuron with one weight per input.
+pub trait Weights {
+	fn inputs(&self) -> usize;
+	fn neurons(&self) -> usize;
+	fn weight(&self, neuron: usize, input: usize) -> f32;
+	fn weight_mut(&mut self, neuron: usize, input: usize) -> &mut f32;
+}
+
+impl<Input: AL, Neurons: NL<Input>> Weights for GenericArray<GenericArray<f32, Input>, Neurons> {
+	fn inputs(&self) -> usize { Input::to_usize() }
+	fn neurons(&self) -> usize { Neurons::to_usize() }
+	fn weight(&self, neuron: usize, input: usize) -> f32 { self[neuron][input] }
+	fn weight_mut(&mut self, neuron: usize, input: usize) -> &mut f32 { &mut self[neuron][input] }
+}
+
+fn fan_in<W: Weights>(weights: &W) -> f32 { weights.inputs() as f32 }
+
+fn fan_out<W: Weights>(weights: &W) -> f32 { weights.neurons() as f32 }
+
+/// calls f for every weight
+fn fill<W: Weights, F: FnMut() -> f32>(weights: &mut W, mut f: F) {
+	for neuron in 0..weights.neurons() {
+		for input in 0..weights.inputs() {
+			*weights.weight_mut(neuron, input) = f();
+		}
+	}
+}
+
+fn uniform<W: Weights, R: Rng>(weights: &mut W, rng: &mut R, limit: f32) {
+	fill(weights, || rng.gen_range(-limit, limit))
+}
+
+fn normal<W: Weights, R: Rng>(weights: &mut W, rng: &mut R, deviation: f32) {
+	fill(weights, || deviation * standard_normal(rng))
+}
+
+/// Box-Muller transform
+pub(crate) fn standard_normal<R: Rng>(rng: &mut R) -> f32 {
+	// gen is in 0..1, so this keeps the logarithm away from 0
+	let u1 = 1. - rng.gen::<f32>();
+	let u2 = rng.gen::<f32>();
+	(-2. * u1.ln()).sqrt() * (2. * PI * u2).cos()
+}
+
+/// Uniform weights in -1..1 and biases in -0.1..0.1, regardless of the layers size.
+///
+/// This is what layers use if no initializer is given.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Uniform {}
+
+impl Initializer for Uniform {
+	fn weights<W: Weights, R: Rng>(weights: &mut W, rng: &mut R) { uniform(weights, rng, 1.) }
+
+	fn bias<R: Rng>(bias: &mut [f32], rng: &mut R) {
+		for bias in bias.iter_mut() {
+			*bias = rng.gen_range(-0.1, 0.1);
+		}
+	}
+}
+
+/// All weights 0. Mostly useful for tests, all neurons of a layer stay identical during training.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Zeros {}
+
+impl Initializer for Zeros {
+	fn weights<W: Weights, R: Rng>(weights: &mut W, _rng: &mut R) { fill(weights, || 0.) }
+}
+
+/// Xavier/Glorot uniform, for [Sigmoid](crate::activation::Sigmoid) and similar activations.
+///
+/// uniform in ±sqrt(6 / (fan_in + fan_out))
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum GlorotUniform {}
+
+impl Initializer for GlorotUniform {
+	fn weights<W: Weights, R: Rng>(weights: &mut W, rng: &mut R) {
+		let limit = (6. / (fan_in(weights) + fan_out(weights))).sqrt();
+		uniform(weights, rng, limit)
+	}
+}
+
+/// Xavier/Glorot normal, for [Sigmoid](crate::activation::Sigmoid) and similar activations.
+///
+/// normal with a standard deviation of sqrt(2 / (fan_in + fan_out))
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum GlorotNormal {}
+
+impl Initializer for GlorotNormal {
+	fn weights<W: Weights, R: Rng>(weights: &mut W, rng: &mut R) {
+		let deviation = (2. / (fan_in(weights) + fan_out(weights))).sqrt();
+		normal(weights, rng, deviation)
+	}
+}
+
+/// He/Kaiming uniform, for [ReLu](crate::activation::ReLu) and its relatives.
+///
+/// uniform in ±sqrt(6 / fan_in)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum HeUniform {}
+
+impl Initializer for HeUniform {
+	fn weights<W: Weights, R: Rng>(weights: &mut W, rng: &mut R) {
+		uniform(weights, rng, (6. / fan_in(weights)).sqrt())
+	}
+}
+
+/// He/Kaiming normal, for [ReLu](crate::activation::ReLu) and its relatives.
+///
+/// normal with a standard deviation of sqrt(2 / fan_in)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum HeNormal {}
+
+impl Initializer for HeNormal {
+	fn weights<W: Weights, R: Rng>(weights: &mut W, rng: &mut R) {
+		normal(weights, rng, (2. / fan_in(weights)).sqrt())
+	}
+}
+
+/// LeCun uniform, keeps the variance of the signal the same from layer to layer.
+///
+/// uniform in ±sqrt(3 / fan_in)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum LeCunUniform {}
+
+impl Initializer for LeCunUniform {
+	fn weights<W: Weights, R: Rng>(weights: &mut W, rng: &mut R) {
+		uniform(weights, rng, (3. / fan_in(weights)).sqrt())
+	}
+}
+
+/// LeCun normal, keeps the variance of the signal the same from layer to layer.
+///
+/// normal with a standard deviation of sqrt(1 / fan_in)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum LeCunNormal {}
+
+impl Initializer for LeCunNormal {
+	fn weights<W: Weights, R: Rng>(weights: &mut W, rng: &mut R) {
+		normal(weights, rng, (1. / fan_in(weights)).sqrt())
+	}
+}
+
+/// A random orthogonal matrix, which neither grows nor shrinks the signal passing through it.
+///
+/// If there are less neurons than inputs the weights of the neurons are orthonormal, otherwise
+/// the weights of each input across the neurons are.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Orthogonal {}
+
+impl Initializer for Orthogonal {
+	fn weights<W: Weights, R: Rng>(weights: &mut W, rng: &mut R) {
+		normal(weights, rng, 1.);
+
+		let inputs = weights.inputs();
+		let neurons = weights.neurons();
+		// gram-schmidt over the shorter side of the matrix
+		let (vectors, length) = if neurons <= inputs {
+			(neurons, inputs)
+		} else {
+			(inputs, neurons)
+		};
+		// (neuron, input) of the i-th value of a vector
+		let index = |vector: usize, i: usize| {
+			if neurons <= inputs {
+				(vector, i)
+			} else {
+				(i, vector)
+			}
+		};
+		let get = |weights: &W, vector, i| {
+			let (neuron, input) = index(vector, i);
+			weights.weight(neuron, input)
+		};
+
+		for vector in 0..vectors {
+			for previous in 0..vector {
+				let dot: f32 = (0..length)
+					.map(|i| get(weights, vector, i) * get(weights, previous, i))
+					.sum();
+				for i in 0..length {
+					let (neuron, input) = index(vector, i);
+					let previous = get(weights, previous, i);
+					*weights.weight_mut(neuron, input) -= dot * previous;
+				}
+			}
+			let norm = (0..length)
+				.map(|i| get(weights, vector, i) * get(weights, vector, i))
+				.sum::<f32>()
+				.sqrt();
+			for i in 0..length {
+				let (neuron, input) = index(vector, i);
+				*weights.weight_mut(neuron, input) /= norm;
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layers::default_rng;
+	use generic_array::typenum::{U3, U5, U64};
+
+	fn deviation<I: Initializer>() -> f32 {
+		let mut weights = GenericArray::<GenericArray<f32, U64>, U64>::default();
+		I::weights(&mut weights, &mut default_rng());
+		let values = || weights.iter().flat_map(|n| n.iter());
+		let mean = values().sum::<f32>() / 4096.;
+		assert!(mean.abs() < 0.02, "{}", mean);
+		(values().map(|w| (w - mean) * (w - mean)).sum::<f32>() / 4096.).sqrt()
+	}
+
+	#[test]
+	fn deviations() {
+		let close = |a: f32, b: f32| (a - b).abs() < 0.05 * b;
+		// uniform in ±limit has a deviation of limit / sqrt(3)
+		assert!(close(deviation::<GlorotUniform>(), (1f32 / 64.).sqrt()));
+		assert!(close(deviation::<GlorotNormal>(), (1f32 / 64.).sqrt()));
+		assert!(close(deviation::<HeUniform>(), (2f32 / 64.).sqrt()));
+		assert!(close(deviation::<HeNormal>(), (2f32 / 64.).sqrt()));
+		assert!(close(deviation::<LeCunUniform>(), (1f32 / 64.).sqrt()));
+		assert!(close(deviation::<LeCunNormal>(), (1f32 / 64.).sqrt()));
+		assert_eq!(deviation::<Zeros>(), 0.);
+	}
+
+	fn assert_orthonormal(vectors: &[&[f32]]) {
+		for (i, a) in vectors.iter().enumerate() {
+			for (j, b) in vectors.iter().enumerate() {
+				let dot: f32 = a.iter().zip(b.iter()).map(|(a, b)| a * b).sum();
+				let expected = if i == j { 1. } else { 0. };
+				assert!((dot - expected).abs() < 1e-5, "{} {} {}", i, j, dot);
+			}
+		}
+	}
+
+	#[test]
+	fn orthogonal() {
+		let mut rng = default_rng();
+
+		let mut wide = GenericArray::<GenericArray<f32, U5>, U3>::default();
+		Orthogonal::weights(&mut wide, &mut rng);
+		let rows: std::vec::Vec<&[f32]> = wide.iter().map(|n| n.as_slice()).collect();
+		assert_orthonormal(&rows);
+
+		let mut tall = GenericArray::<GenericArray<f32, U3>, U5>::default();
+		Orthogonal::weights(&mut tall, &mut rng);
+		let columns: std::vec::Vec<std::vec::Vec<f32>> = (0..3)
+			.map(|i| tall.iter().map(|n| n[i]).collect())
+			.collect();
+		let columns: std::vec::Vec<&[f32]> = columns.iter().map(|c| c.as_slice()).collect();
+		assert_orthonormal(&columns);
+	}
+}
@@ -5,14 +5,16 @@
 //! [InnerLayer.push()](InnerLayer#method.push) inner layers.
 //! the resulting layer is the input layer.
 //!
+//! The [network!](crate::network) macro does the same, but reads from the input layer down.
+//!
 //! ## Training
 //!
-//! You would then train the resulting network using [Backpropagaiton](Layer#method.backprop) by showing
+//! You would then train the resulting network using [Backpropagaiton](Network#method.backprop) by showing
 //! it inputs and expected outputs.
 //!
 //!
 //! ## Use
-//! Once trained you can ask it to [calculate](Layer#tymethod.calculate) an output for any input.
+//! Once trained you can ask it to [calculate](Network#method.calculate) an output for any input.
 //!
 //! Layers, and therefore the whole Network, can be serialized and deserialized at any time, using
 //! SerDe.
@@ -24,161 +26,338 @@
 //!
 //! Only the input layer needs to have both its size and its inputs provided.
 //!
+//! ## Randomness
+//! Weights start out random. The constructors without an rng all use [default_rng], so the same
+//! network is created every time. Use the `_with_rng` variants to pick a different seed, or to
+//! share one rng between all layers of a network so layers of the same shape do not start out
+//! identical. The `_with_init` variants additionally take an [Initializer] that scales the
+//! weights to the size of the layer.
+//!
 //! Check out the example directory if you are still unsure.
 
 use generic_array::{ArrayLength, GenericArray};
 
-use rand::Rng;
-
-use crate::activation::Activation;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+
+use crate::{
+	activation::LayerActivation,
+	initializer::{Initializer, Uniform},
+	loss::{Loss, Mse},
+	optimizer::{Optimizer, Params, Sgd},
+	regularizer::Regularizer,
+	schedule::LearningRateSchedule,
+};
 use core::fmt::Debug;
 
 use serde::{de::DeserializeOwned, Serialize};
 
+/// the seed of [default_rng]
+pub const DEFAULT_SEED: u64 = 0x5eed;
+
+/// A small, fast rng that works without the standard library, seeded with [DEFAULT_SEED].
+///
+/// Two networks initialized from the same seed are bit-identical.
+pub fn default_rng() -> Pcg32 { Pcg32::seed_from_u64(DEFAULT_SEED) }
+
 pub trait AL = ArrayLength<f32> + Debug + Clone;
 pub trait NL<Input: ArrayLength<f32>> =
 	ArrayLength<GenericArray<f32, Input>> + ArrayLength<f32> + Debug + Clone;
 
-fn loss<Len: AL>(errors: &GenericArray<f32, Len>) -> f32 {
-	let sum: f32 = errors.iter().map(|e| e * e).sum();
-	sum.sqrt()
+pub(crate) mod sealed {
+	/// keeps [Differentiable](super::Differentiable) from being implemented outside of this crate
+	pub trait Sealed {}
 }
-/// A Layer takes a list of inputs, multiplexes and weights them onto its Neurons,
-/// and produces a list of outputs, one for each neuron
+
+/// A stack of layers, from the layer taking the inputs down to the final layer producing the
+/// output.
 ///
-/// Functions starting with an underscore are generally not to be called manually
-// ToDo: split into two traits, one with a user facing interface and one with the implementation
-// details
-pub trait Layer<
-	/// number of inputs this layer takes
+/// This is implemented for every layer, since every layer is the top of the stack below it.
+pub trait Network<
+	// number of inputs this layer takes
 	Input: AL,
-	/// number of neurons this layer has
-	Neurons: NL<Input>,
-	/// number of neurons of the final layer
+	// number of neurons of the final layer
 	FinalOut: AL,
-	/// activation function
-	A: Activation,
-> :Debug+Clone+Serialize+DeserializeOwned {
+>: Differentiable<Input, FinalOut> {
 	/// runs the inputs through this and lower layers, resulting in the output
-	fn calculate(&self, inputs: &GenericArray<f32, Input>) -> GenericArray<f32, FinalOut>;
-
-	/// reduces the inputs to a single input per neuron using the weights.
-	/// i.e. calculates the input to the activation functions for the neurons
-	fn weight(&self, inputs: &GenericArray<f32, Input>) -> GenericArray<f32, Neurons>;
+	fn calculate(&self, inputs: &GenericArray<f32, Input>) -> GenericArray<f32, FinalOut> {
+		self._calculate(inputs)
+	}
 
-	/// runs only this layers calculation, not recursing to deeper layers
-	fn step(&self, weighted_inputs: &GenericArray<f32, Neurons>) -> GenericArray<f32, Neurons>;
+	/// calculates the output for the inputs and rates it against the correct output using the
+	/// loss of the final layer, without learning anything
+	fn evaluate(&self, inputs: &GenericArray<f32, Input>, correct_output: &GenericArray<f32, FinalOut>) -> f32 {
+		Self::_loss(&self.calculate(inputs), correct_output)
+	}
 
 	/// pass in the input and the expected output. calculates the error for each neuron
 	/// and corrects itself
 	/// speed should be 0..1 and modifies how strongly the weights are adjusted
+	///
+	/// returns the error of the inputs and the loss of the final layer
 	fn backprop(&mut self, input: &GenericArray<f32, Input>, correct_output: &GenericArray<f32, FinalOut>, speed: f32
-		) -> (GenericArray<f32, Input>, GenericArray<f32, FinalOut>) {
-		let weighted_inputs = self.weight(input);
-		let output = self.step(&weighted_inputs);
+		) -> (GenericArray<f32, Input>, f32) {
+		let result = self.accumulate(input, correct_output);
+		self.apply_batch(1, speed);
+		result
+	}
 
-		let (own_error, final_error) = self._get_error(output, correct_output, speed);
+	/// like [backprop](Network::backprop), but only sums up the deltas without correcting
+	/// itself. Call [apply_batch](Network::apply_batch) once the whole batch has been accumulated.
+	fn accumulate(&mut self, input: &GenericArray<f32, Input>, correct_output: &GenericArray<f32, FinalOut>
+		) -> (GenericArray<f32, Input>, f32) {
+		self._accumulate(input, correct_output)
+	}
 
-		let deltas = self._weight_errors(own_error, &weighted_inputs);
-		let previous_errors = self._pre_error(&deltas);
-		self._apply_deltas(deltas, &input, speed);
+	/// corrects the weights of this and lower layers by the average of the deltas of the last
+	/// batch_size [accumulate](Network::accumulate) calls
+	fn apply_batch(&mut self, batch_size: usize, speed: f32) {
+		if batch_size > 0 {
+			self._apply_deltas(batch_size, speed);
+		}
+	}
 
-		// the errors are just passed up for informational purposes, so a training alg can
-		// determine how wrong the network is without running an extra recognition step
-		// its not actually used for backprop at all.
-		(previous_errors, final_error)
+	/// like [accumulate](Network::accumulate) for a whole batch at once, so layers like
+	/// [BatchNorm](crate::normalization::BatchNorm) can use the statistics of the batch.
+	///
+	/// returns the errors of the inputs and the average loss of the final layer
+	fn accumulate_batch<const B: usize>(&mut self, inputs: &[GenericArray<f32, Input>; B], correct_outputs: &[GenericArray<f32, FinalOut>; B]
+		) -> ([GenericArray<f32, Input>; B], f32) {
+		self._accumulate_batch(inputs, |outputs| {
+			let mut loss = 0.;
+			let errors = map_batch(outputs.clone(), |i, output| {
+				let (error, example_loss) = Self::_error(&output, &correct_outputs[i]);
+				loss += example_loss / B as f32;
+				error
+			});
+			(errors, loss)
+		})
 	}
 
+	/// like [backprop](Network::backprop) for a whole batch at once, see
+	/// [accumulate_batch](Network::accumulate_batch)
+	///
+	/// returns the average loss of the final layer
+	fn backprop_batch<const B: usize>(&mut self, inputs: &[GenericArray<f32, Input>; B], correct_outputs: &[GenericArray<f32, FinalOut>; B], speed: f32) -> f32 {
+		let (_, loss) = self.accumulate_batch(inputs, correct_outputs);
+		self.apply_batch(B, speed);
+		loss
+	}
 
-	/// gets this layers error, either by comparing with correct output or by calling lower layers
-	fn _get_error(&mut self, output: GenericArray<f32, Neurons>, correct_output: &GenericArray<f32, FinalOut>, speed: f32
-		) -> (GenericArray<f32, Neurons>, GenericArray<f32, FinalOut>);
+	/// trains the network on the lesson for a number of iterations.
+	///
+	/// the weights are corrected once every batch_size examples, a batch_size of 1 corrects them
+	/// after every example.
+	/// the schedule decides the speed of each iteration.
+	/// the callback gets the iteration and the average loss of it.
+	///
+	/// for validation and early stopping see [Trainer](crate::trainer::Trainer)
+	fn teach<S: LearningRateSchedule, F: FnMut(usize, f32), I: IntoIterator<Item = (GenericArray<f32, Input>, GenericArray<f32, FinalOut>)>>(&mut self, lesson : I, iterations: usize, batch_size: usize, mut schedule: S, mut callback: F)
+		where <I as IntoIterator>::IntoIter: Clone
+	{
+		let lesson = lesson.into_iter();
+		for i in 0..iterations {
+			let speed = schedule.speed(i, iterations);
+			let avg_loss = self.teach_epoch(lesson.clone(), batch_size, speed);
+			schedule.end_epoch(avg_loss);
+			callback(i, avg_loss);
+		}
+	}
 
-	/// weights errors relative to activation. gets called by backprop, don't call this manually
-	fn _weight_errors(&self, mut error: GenericArray<f32, Neurons>, weighted_inputs: &GenericArray<f32, Neurons>) -> GenericArray<f32, Neurons> {
-		error.iter_mut().zip(weighted_inputs)
-			.for_each(|(error, &input)| {
-				// multiply with the derivate to get the delta
-				*error *= A::derivate(A::activate(input));
-			});
-		error
+	/// runs through the lesson once, correcting the weights every batch_size examples.
+	/// returns the average loss, plus the penalty of layers whose [Regularizer] reports it
+	fn teach_epoch<I: IntoIterator<Item = (GenericArray<f32, Input>, GenericArray<f32, FinalOut>)>>(&mut self, lesson : I, batch_size: usize, speed: f32) -> f32 {
+		let batch_size = batch_size.max(1);
+		let mut avg_loss = 0f32;
+		let mut batch = 0;
+		for (pos, (input, output)) in lesson.into_iter().enumerate() {
+			let (_, loss) = self.accumulate(&input, &output);
+			avg_loss = avg_loss + (loss - avg_loss) / ((pos+1) as f32);
+			batch += 1;
+			if batch == batch_size {
+				self.apply_batch(batch, speed);
+				batch = 0;
+			}
+		}
+		// the last batch may be incomplete
+		self.apply_batch(batch, speed);
+		avg_loss + self._penalty()
+	}
+
+	/// like [teach_epoch](Network::teach_epoch), but passes each batch of B examples through the
+	/// network at once using [backprop_batch](Network::backprop_batch).
+	///
+	/// a last batch of less than B examples is left out
+	fn teach_batches<const B: usize, I: IntoIterator<Item = (GenericArray<f32, Input>, GenericArray<f32, FinalOut>)>>(&mut self, lesson : I, speed: f32) -> f32 {
+		let mut inputs: [GenericArray<f32, Input>; B] = core::array::from_fn(|_| GenericArray::default());
+		let mut outputs: [GenericArray<f32, FinalOut>; B] = core::array::from_fn(|_| GenericArray::default());
+		let mut avg_loss = 0f32;
+		let mut batches = 0;
+		let mut batch = 0;
+		for (input, output) in lesson {
+			inputs[batch] = input;
+			outputs[batch] = output;
+			batch += 1;
+			if batch == B {
+				let loss = self.backprop_batch(&inputs, &outputs, speed);
+				batches += 1;
+				avg_loss = avg_loss + (loss - avg_loss) / (batches as f32);
+				batch = 0;
+			}
+		}
+		avg_loss + self._penalty()
 	}
+}
 
-	/// modifies own weights by given deltas
-	fn _apply_deltas(&mut self, deltas: GenericArray<f32, Neurons>, inputs: &GenericArray<f32, Input>, speed: f32);
+impl<Input: AL, FinalOut: AL, T: Differentiable<Input, FinalOut>> Network<Input, FinalOut> for T {}
+
+/// maps every example of a batch, together with its index
+pub(crate) fn map_batch<T, U, F: FnMut(usize, T) -> U, const B: usize>(
+	batch: [T; B],
+	mut f: F,
+) -> [U; B] {
+	let mut i = 0;
+	batch.map(|example| {
+		let out = f(i, example);
+		i += 1;
+		out
+	})
+}
 
-	/// calculates the previous layers errors from this layers errors and weights
-	fn _pre_error(&self, deltas: &GenericArray<f32, Neurons>) -> GenericArray<f32, Input>;
+/// The implementation details of a layer and all layers below it, use [Network] instead.
+///
+/// Each layer only has to provide what it actually needs, how it turns the error of its outputs
+/// into deltas is up to the layer.
+pub trait Differentiable<Input: AL, FinalOut: AL>:
+	sealed::Sealed + Debug + Clone + Serialize + DeserializeOwned
+{
+	/// runs the inputs through this and lower layers
+	fn _calculate(&self, inputs: &GenericArray<f32, Input>) -> GenericArray<f32, FinalOut>;
 
-	fn teach<F: FnMut(usize, f32), I: IntoIterator<Item = (GenericArray<f32, Input>, GenericArray<f32, FinalOut>)>>(&mut self, lesson : I, iterations: usize, mut callback: F)
-		where <I as IntoIterator>::IntoIter: Clone
-	{
-            let lesson = lesson.into_iter();
-            // change modification speed over time
-            // start quick, slow down over time
-            // todo: make this modifiable by caller
-            let factor = |progress| {
-                    let percentage = (progress as f32)/(iterations as f32);
-                    //let min = 0.08 * percentage;
-                    let min = 0.4 * percentage;
-                    let max = 0.9 * (1.-percentage);
-                    min + max
-            };
-            for i in 0..iterations {
-                let mut avg_loss = 0f32;
-                let iter = lesson.clone().enumerate();
-                for (pos, (input, output)) in iter {
-                            let (_, err) = self.backprop(&input, &output, factor(i));
-                            avg_loss = avg_loss + (loss(&err) - avg_loss) / ((pos+1) as f32);
-                    }
-                callback(i, avg_loss);
-            }
+	/// the loss of the final layer
+	fn _loss(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> f32;
+
+	/// the error of the output of the final layer, which is its negated loss gradient, and its
+	/// loss
+	fn _error(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> (GenericArray<f32, FinalOut>, f32);
+
+	/// runs the inputs through this and lower layers, adds the deltas for all their weights to
+	/// the ones of the current batch and returns the error of the inputs and the loss of the final
+	/// layer
+	fn _accumulate(
+		&mut self,
+		inputs: &GenericArray<f32, Input>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> (GenericArray<f32, Input>, f32) {
+		self._accumulate_with(inputs, |output| Self::_error(output, correct_output))
 	}
+
+	/// like [_accumulate](Differentiable::_accumulate), but objective decides the error and the
+	/// loss of the final output instead of the loss of the final layer
+	fn _accumulate_with<F>(
+		&mut self,
+		inputs: &GenericArray<f32, Input>,
+		objective: F,
+	) -> (GenericArray<f32, Input>, f32)
+	where
+		F: FnOnce(&GenericArray<f32, FinalOut>) -> (GenericArray<f32, FinalOut>, f32);
+
+	/// like [_accumulate_with](Differentiable::_accumulate_with) for a whole batch of B examples
+	/// at once, objective gets the final outputs of all of them. Layers whose outputs depend on
+	/// the other examples of the batch, like [BatchNorm](crate::normalization::BatchNorm), need
+	/// every layer above them to pass on the batch as a whole.
+	fn _accumulate_batch<const B: usize, F>(
+		&mut self,
+		inputs: &[GenericArray<f32, Input>; B],
+		objective: F,
+	) -> ([GenericArray<f32, Input>; B], f32)
+	where
+		F: FnOnce(&[GenericArray<f32, FinalOut>; B]) -> ([GenericArray<f32, FinalOut>; B], f32);
+
+	/// modifies own and lower layers weights by the stored deltas and resets them
+	fn _apply_deltas(&mut self, batch_size: usize, speed: f32);
+
+	/// calls f(param, delta) for every trainable parameter of this and lower layers together with
+	/// its delta of the current batch, always in the same order
+	fn _params_mut<F: FnMut(&mut f32, &mut f32)>(&mut self, f: &mut F);
+
+	/// the penalty of the weights of this and lower layers whose [Regularizer] reports it
+	fn _penalty(&self) -> f32;
 }
 
 /// The final layer of a Network.
+///
+/// It compares its output to the correct output using the [Loss] L and adjusts its weights using
+/// the [Optimizer] O.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(bound = "Neurons: NL<Input>, Input: AL")]
-pub struct OutputLayer<A: Activation, Neurons: NL<Input>, Input: AL> {
-	weights: GenericArray<GenericArray<f32, Input>, Neurons>,
-	bias: GenericArray<f32, Neurons>,
-	phantom: core::marker::PhantomData<A>,
+pub struct OutputLayer<
+	A: LayerActivation,
+	Neurons: NL<Input>,
+	Input: AL,
+	L: Loss = Mse,
+	O: Optimizer = Sgd,
+> {
+	pub(crate) weights: GenericArray<GenericArray<f32, Input>, Neurons>,
+	pub(crate) bias: GenericArray<f32, Neurons>,
+	pub(crate) weight_state: O::State<GenericArray<GenericArray<f32, Input>, Neurons>>,
+	pub(crate) bias_state: O::State<GenericArray<f32, Neurons>>,
+	pub(crate) regularizer: Regularizer,
+	// deltas of the current batch
+	#[serde(skip)]
+	pub(crate) weight_deltas: GenericArray<GenericArray<f32, Input>, Neurons>,
+	#[serde(skip)]
+	pub(crate) bias_deltas: GenericArray<f32, Neurons>,
+	phantom: core::marker::PhantomData<(A, L, O)>,
 }
 
-impl<A: Activation, Neurons: NL<Input>, Input: AL> OutputLayer<A, Neurons, Input> {
-	pub fn new() -> Self {
+impl<A: LayerActivation, Neurons: NL<Input>, Input: AL, L: Loss, O: Optimizer>
+	OutputLayer<A, Neurons, Input, L, O>
+{
+	pub fn new() -> Self { Self::new_with_rng(&mut default_rng()) }
+
+	/// draws the initial weights from rng using the [Uniform] initializer
+	pub fn new_with_rng<R: Rng>(rng: &mut R) -> Self { Self::new_with_init::<Uniform, R>(rng) }
+
+	/// draws the initial weights from rng using the [Initializer] I
+	pub fn new_with_init<I: Initializer, R: Rng>(rng: &mut R) -> Self {
 		let mut out = Self {
 			weights: GenericArray::default(),
 			bias: GenericArray::default(),
-			phantom: core::marker::PhantomData::default(),
+			weight_state: Default::default(),
+			bias_state: Default::default(),
+			regularizer: Regularizer::default(),
+			weight_deltas: GenericArray::default(),
+			bias_deltas: GenericArray::default(),
+			phantom: core::marker::PhantomData,
 		};
-		let mut rng = rand::rngs::OsRng;
-		for neuron in out.weights.as_mut_slice() {
-			for weight in neuron.as_mut_slice() {
-				*weight = rng.gen_range(-1., 1.)
-			}
-		}
-		for bias in out.bias.iter_mut() {
-			*bias = rng.gen_range(-0.1, 0.1);
-		}
+		I::weights(&mut out.weights, rng);
+		I::bias(&mut out.bias, rng);
 		out
 	}
+
+	/// regularizes the weights of this layer using regularizer
+	pub fn with_regularizer(self, regularizer: Regularizer) -> Self { Self { regularizer, ..self } }
 }
 
-impl<A: Activation, Input: AL, Neurons: NL<Input>> Layer<Input, Neurons, Neurons, A>
-	for OutputLayer<A, Neurons, Input>
+impl<A: LayerActivation, Neurons: NL<Input>, Input: AL, L: Loss, O: Optimizer> Default
+	for OutputLayer<A, Neurons, Input, L, O>
 {
-	fn calculate(&self, inputs: &GenericArray<f32, Input>) -> GenericArray<f32, Neurons> {
-		self.step(&self.weight(inputs))
-	}
-	fn step(&self, inputs: &GenericArray<f32, Neurons>) -> GenericArray<f32, Neurons> {
-		inputs
-			.into_iter()
-			.map(|&input| A::activate(input))
-			.collect()
-	}
+	fn default() -> Self { Self::new() }
+}
 
-	fn weight(&self, inputs: &GenericArray<f32, Input>) -> GenericArray<f32, Neurons> {
+impl<A: LayerActivation, Neurons: NL<Input>, Input: AL, L: Loss, O: Optimizer>
+	OutputLayer<A, Neurons, Input, L, O>
+{
+	/// reduces the inputs to a single input per neuron using the weights.
+	/// i.e. calculates the input to the activation functions for the neurons
+	pub(crate) fn weight(&self, inputs: &GenericArray<f32, Input>) -> GenericArray<f32, Neurons> {
 		debug_assert_eq!(self.weights.len(), self.bias.len());
 		self.weights
 			.iter()
@@ -193,51 +372,47 @@ impl<A: Activation, Input: AL, Neurons: NL<Input>> Layer<Input, Neurons, Neurons
 			.collect()
 	}
 
-	fn _get_error(
+	/// runs only this layers calculation, not recursing to deeper layers
+	pub(crate) fn step(&self, inputs: &GenericArray<f32, Neurons>) -> GenericArray<f32, Neurons> {
+		let mut outputs = GenericArray::default();
+		A::activate_layer(inputs, &mut outputs);
+		outputs
+	}
+
+	/// carries the error back through the activation to get the deltas, stores them and returns
+	/// the error of the inputs
+	pub(crate) fn backward(
 		&mut self,
-		mut output: GenericArray<f32, Neurons>,
-		correct_output: &GenericArray<f32, Neurons>,
-		_speed: f32,
-	) -> (GenericArray<f32, Neurons>, GenericArray<f32, Neurons>) {
-		// TODO: this might need to be generic
-		output
-			.iter_mut()
-			.zip(correct_output)
-			.for_each(|(output, &correct_output)| {
-				// subtract the output from the expected output to get the error
-				*output = correct_output - *output;
-			});
-		let error = output;
-		(error.clone(), error)
+		inputs: &GenericArray<f32, Input>,
+		weighted_inputs: &GenericArray<f32, Neurons>,
+		output: &GenericArray<f32, Neurons>,
+		mut error: GenericArray<f32, Neurons>,
+	) -> GenericArray<f32, Input> {
+		A::jvp(weighted_inputs, output, &mut error);
+		let pre_error = self.pre_error(&error);
+		self.store_deltas(error, inputs);
+		pre_error
 	}
 
-	fn _apply_deltas(
+	/// adds the deltas for own weights to the ones of the current batch
+	pub(crate) fn store_deltas(
 		&mut self,
-		mut deltas: GenericArray<f32, Neurons>,
+		deltas: GenericArray<f32, Neurons>,
 		inputs: &GenericArray<f32, Input>,
-		speed: f32,
 	) {
-		// then add to own weights
-		debug_assert_eq!(deltas.len(), self.weights.len());
-		debug_assert_eq!(self.bias.len(), self.weights.len());
-		deltas.iter_mut().for_each(|d| *d *= speed);
-		for ((neuron, delta), bias) in self
-			.weights
-			.iter_mut()
-			.zip(deltas)
-			.zip(self.bias.iter_mut())
-		{
-			debug_assert_eq!(neuron.len(), inputs.len());
-
-			for (weight, &input_activation) in neuron.iter_mut().zip(inputs) {
-				*weight += delta * input_activation;
-			}
-			// bias input activation is always 1
-			*bias += delta;
-		}
+		store_deltas(
+			&mut self.weight_deltas,
+			&mut self.bias_deltas,
+			deltas,
+			inputs,
+		)
 	}
 
-	fn _pre_error(&self, deltas: &GenericArray<f32, Neurons>) -> GenericArray<f32, Input> {
+	/// calculates the previous layers errors from this layers errors and weights
+	pub(crate) fn pre_error(
+		&self,
+		deltas: &GenericArray<f32, Neurons>,
+	) -> GenericArray<f32, Input> {
 		// first calculate the weighted deltas (basically inverse weighted inputs)
 		let mut inverse_delta = GenericArray::<f32, Input>::default();
 
@@ -251,86 +426,372 @@ impl<A: Activation, Input: AL, Neurons: NL<Input>> Layer<Input, Neurons, Neurons
 	}
 }
 
+impl<A: LayerActivation, Neurons: NL<Input>, Input: AL, L: Loss, O: Optimizer> sealed::Sealed
+	for OutputLayer<A, Neurons, Input, L, O>
+{
+}
+
+impl<A: LayerActivation, Input: AL, Neurons: NL<Input>, L: Loss, O: Optimizer>
+	Differentiable<Input, Neurons> for OutputLayer<A, Neurons, Input, L, O>
+{
+	fn _calculate(&self, inputs: &GenericArray<f32, Input>) -> GenericArray<f32, Neurons> {
+		self.step(&self.weight(inputs))
+	}
+
+	fn _loss(
+		output: &GenericArray<f32, Neurons>,
+		correct_output: &GenericArray<f32, Neurons>,
+	) -> f32 {
+		L::loss(output, correct_output)
+	}
+
+	fn _error(
+		output: &GenericArray<f32, Neurons>,
+		correct_output: &GenericArray<f32, Neurons>,
+	) -> (GenericArray<f32, Neurons>, f32) {
+		// the gradient points uphill, so invert it to get the error
+		let mut error = GenericArray::default();
+		L::gradient(output, correct_output, &mut error);
+		error.iter_mut().for_each(|e| *e = -*e);
+
+		// the loss is just passed up for informational purposes, so a training alg can
+		// determine how wrong the network is without running an extra recognition step
+		// its not actually used for backprop at all.
+		(error, L::loss(output, correct_output))
+	}
+
+	fn _accumulate_with<F>(
+		&mut self,
+		inputs: &GenericArray<f32, Input>,
+		objective: F,
+	) -> (GenericArray<f32, Input>, f32)
+	where
+		F: FnOnce(&GenericArray<f32, Neurons>) -> (GenericArray<f32, Neurons>, f32),
+	{
+		let weighted_inputs = self.weight(inputs);
+		let output = self.step(&weighted_inputs);
+		let (error, loss) = objective(&output);
+		(
+			self.backward(inputs, &weighted_inputs, &output, error),
+			loss,
+		)
+	}
+
+	fn _accumulate_batch<const B: usize, F>(
+		&mut self,
+		inputs: &[GenericArray<f32, Input>; B],
+		objective: F,
+	) -> ([GenericArray<f32, Input>; B], f32)
+	where
+		F: FnOnce(&[GenericArray<f32, Neurons>; B]) -> ([GenericArray<f32, Neurons>; B], f32),
+	{
+		let weighted_inputs = inputs.each_ref().map(|inputs| self.weight(inputs));
+		let outputs = weighted_inputs
+			.each_ref()
+			.map(|weighted| self.step(weighted));
+		let (errors, loss) = objective(&outputs);
+		let pre_errors = map_batch(errors, |i, error| {
+			self.backward(&inputs[i], &weighted_inputs[i], &outputs[i], error)
+		});
+		(pre_errors, loss)
+	}
+
+	fn _apply_deltas(&mut self, batch_size: usize, speed: f32) {
+		self.regularizer.apply_deltas::<O, _>(
+			&mut self.weights,
+			&mut self.weight_state,
+			&mut self.weight_deltas,
+			batch_size,
+			speed,
+		);
+		apply_deltas::<O, _>(
+			&mut self.bias,
+			&mut self.bias_state,
+			&mut self.bias_deltas,
+			batch_size,
+			speed,
+		);
+	}
+
+	fn _params_mut<F: FnMut(&mut f32, &mut f32)>(&mut self, f: &mut F) {
+		params_mut(&mut self.weights, &mut self.weight_deltas, f);
+		params_mut(&mut self.bias, &mut self.bias_deltas, f);
+	}
+
+	fn _penalty(&self) -> f32 { self.regularizer.reported_penalty(&self.weights) }
+}
+
+/// every weight moves by the delta of its neuron times the input it gets multiplied with
+pub(crate) fn store_deltas<Input: AL, Neurons: NL<Input>>(
+	weight_deltas: &mut GenericArray<GenericArray<f32, Input>, Neurons>,
+	bias_deltas: &mut GenericArray<f32, Neurons>,
+	deltas: GenericArray<f32, Neurons>,
+	inputs: &GenericArray<f32, Input>,
+) {
+	debug_assert_eq!(deltas.len(), weight_deltas.len());
+	for ((neuron, delta), bias) in weight_deltas
+		.iter_mut()
+		.zip(deltas)
+		.zip(bias_deltas.iter_mut())
+	{
+		debug_assert_eq!(neuron.len(), inputs.len());
+
+		for (weight, &input_activation) in neuron.iter_mut().zip(inputs) {
+			*weight += delta * input_activation;
+		}
+		// bias input activation is always 1
+		*bias += delta;
+	}
+}
+
+/// moves the params by the average of the deltas collected during the batch, then resets the
+/// deltas for the next batch
+pub(crate) fn apply_deltas<O: Optimizer, P: Params>(
+	params: &mut P,
+	state: &mut O::State<P>,
+	deltas: &mut P,
+	batch_size: usize,
+	speed: f32,
+) {
+	let scale = 1. / batch_size as f32;
+	deltas.values_mut().for_each(|d| *d *= scale);
+	O::update(state, params, deltas, speed);
+	*deltas = P::default();
+}
+
+/// calls f(param, delta) for each of the params and their deltas
+pub(crate) fn params_mut<P: Params, F: FnMut(&mut f32, &mut f32)>(
+	params: &mut P,
+	deltas: &mut P,
+	f: &mut F,
+) {
+	for (param, delta) in params.values_mut().zip(deltas.values_mut()) {
+		f(param, delta);
+	}
+}
+
 /// a layer that stacks another layer inside itself (which may then recursively stack another and so on)
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(bound = "Neurons: NL<Input>, Input: AL, NextN: NL<Neurons>, FinalOut: AL")]
+#[serde(bound = "Neurons: NL<Input>, Input: AL, FinalOut: AL")]
 pub struct InnerLayer<
-	A: Activation,
+	A: LayerActivation,
 	Neurons: NL<Input>,
 	Input: AL,
-	NextN: NL<Neurons>,
 	FinalOut: AL,
-	NextA: Activation,
-	Next: Layer<Neurons, NextN, FinalOut, NextA>,
+	Next: Differentiable<Neurons, FinalOut>,
+	O: Optimizer = Sgd,
 > {
-	inner: OutputLayer<A, Neurons, Input>,
-	next: Next,
-	phantom: core::marker::PhantomData<(NextN, FinalOut, NextA)>,
+	pub(crate) inner: OutputLayer<A, Neurons, Input, Mse, O>,
+	pub(crate) next: Next,
+	phantom: core::marker::PhantomData<FinalOut>,
 }
 
 impl<
-		A: Activation,
+		A: LayerActivation,
 		Neurons: NL<Input>,
 		Input: AL,
-		NextN: NL<Neurons>,
 		FinalOut: AL,
-		NextA: Activation,
-		Next: Layer<Neurons, NextN, FinalOut, NextA>,
-	> InnerLayer<A, Neurons, Input, NextN, FinalOut, NextA, Next>
+		Next: Differentiable<Neurons, FinalOut>,
+		O: Optimizer,
+	> InnerLayer<A, Neurons, Input, FinalOut, Next, O>
 {
 	/// Pushes this layer on top of an existing layer.
-	pub fn push(next: Next) -> Self {
+	pub fn push(next: Next) -> Self { Self::push_with_rng(next, &mut default_rng()) }
+
+	/// like [push](InnerLayer::push), drawing the initial weights from rng
+	pub fn push_with_rng<R: Rng>(next: Next, rng: &mut R) -> Self {
+		Self::push_with_init::<Uniform, R>(next, rng)
+	}
+
+	/// like [push](InnerLayer::push), drawing the initial weights from rng using the
+	/// [Initializer] I
+	pub fn push_with_init<I: Initializer, R: Rng>(next: Next, rng: &mut R) -> Self {
 		Self {
 			next,
-			inner: OutputLayer::new(),
-			phantom: core::marker::PhantomData::default(),
+			inner: OutputLayer::new_with_init::<I, R>(rng),
+			phantom: core::marker::PhantomData,
 		}
 	}
+
+	/// regularizes the weights of this layer, but not the ones of lower layers, using
+	/// regularizer
+	pub fn with_regularizer(mut self, regularizer: Regularizer) -> Self {
+		self.inner.regularizer = regularizer;
+		self
+	}
 }
 
 impl<
-		A: Activation,
+		A: LayerActivation,
 		Neurons: NL<Input>,
 		Input: AL,
-		NextN: NL<Neurons>,
 		FinalOut: AL,
-		NextA: Activation,
-		Next: Layer<Neurons, NextN, FinalOut, NextA>,
-	> Layer<Input, Neurons, FinalOut, A>
-	for InnerLayer<A, Neurons, Input, NextN, FinalOut, NextA, Next>
+		Next: Differentiable<Neurons, FinalOut> + Default,
+		O: Optimizer,
+	> Default for InnerLayer<A, Neurons, Input, FinalOut, Next, O>
 {
-	fn calculate(&self, inputs: &GenericArray<f32, Input>) -> GenericArray<f32, FinalOut> {
-		let own_output = self.step(&self.weight(inputs));
-		self.next.calculate(&own_output)
-	}
+	fn default() -> Self { Self::push(Next::default()) }
+}
+
+impl<
+		A: LayerActivation,
+		Neurons: NL<Input>,
+		Input: AL,
+		FinalOut: AL,
+		Next: Differentiable<Neurons, FinalOut>,
+		O: Optimizer,
+	> sealed::Sealed for InnerLayer<A, Neurons, Input, FinalOut, Next, O>
+{
+}
 
-	fn weight(&self, inputs: &GenericArray<f32, Input>) -> GenericArray<f32, Neurons> {
-		self.inner.weight(inputs)
+impl<
+		A: LayerActivation,
+		Neurons: NL<Input>,
+		Input: AL,
+		FinalOut: AL,
+		Next: Differentiable<Neurons, FinalOut>,
+		O: Optimizer,
+	> Differentiable<Input, FinalOut> for InnerLayer<A, Neurons, Input, FinalOut, Next, O>
+{
+	fn _calculate(&self, inputs: &GenericArray<f32, Input>) -> GenericArray<f32, FinalOut> {
+		let own_output = self.inner.step(&self.inner.weight(inputs));
+		self.next._calculate(&own_output)
 	}
 
-	fn step(&self, inputs: &GenericArray<f32, Neurons>) -> GenericArray<f32, Neurons> {
-		self.inner.step(inputs)
+	fn _loss(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> f32 {
+		Next::_loss(output, correct_output)
 	}
 
-	fn _get_error(
-		&mut self,
-		output: GenericArray<f32, Neurons>,
+	fn _error(
+		output: &GenericArray<f32, FinalOut>,
 		correct_output: &GenericArray<f32, FinalOut>,
-		speed: f32,
-	) -> (GenericArray<f32, Neurons>, GenericArray<f32, FinalOut>) {
-		self.next.backprop(&output, correct_output, speed)
+	) -> (GenericArray<f32, FinalOut>, f32) {
+		Next::_error(output, correct_output)
 	}
 
-	fn _apply_deltas(
+	fn _accumulate_with<F>(
 		&mut self,
-		deltas: GenericArray<f32, Neurons>,
 		inputs: &GenericArray<f32, Input>,
-		speed: f32,
-	) {
-		self.inner._apply_deltas(deltas, inputs, speed)
+		objective: F,
+	) -> (GenericArray<f32, Input>, f32)
+	where
+		F: FnOnce(&GenericArray<f32, FinalOut>) -> (GenericArray<f32, FinalOut>, f32),
+	{
+		let weighted_inputs = self.inner.weight(inputs);
+		let output = self.inner.step(&weighted_inputs);
+		let (error, loss) = self.next._accumulate_with(&output, objective);
+		(
+			self.inner
+				.backward(inputs, &weighted_inputs, &output, error),
+			loss,
+		)
+	}
+
+	fn _accumulate_batch<const B: usize, F>(
+		&mut self,
+		inputs: &[GenericArray<f32, Input>; B],
+		objective: F,
+	) -> ([GenericArray<f32, Input>; B], f32)
+	where
+		F: FnOnce(&[GenericArray<f32, FinalOut>; B]) -> ([GenericArray<f32, FinalOut>; B], f32),
+	{
+		let weighted_inputs = inputs.each_ref().map(|inputs| self.inner.weight(inputs));
+		let outputs = weighted_inputs
+			.each_ref()
+			.map(|weighted| self.inner.step(weighted));
+		let (errors, loss) = self.next._accumulate_batch(&outputs, objective);
+		let pre_errors = map_batch(errors, |i, error| {
+			self.inner
+				.backward(&inputs[i], &weighted_inputs[i], &outputs[i], error)
+		});
+		(pre_errors, loss)
 	}
 
-	fn _pre_error(&self, deltas: &GenericArray<f32, Neurons>) -> GenericArray<f32, Input> {
-		self.inner._pre_error(deltas)
+	fn _apply_deltas(&mut self, batch_size: usize, speed: f32) {
+		self.inner._apply_deltas(batch_size, speed);
+		self.next._apply_deltas(batch_size, speed);
+	}
+
+	fn _params_mut<F: FnMut(&mut f32, &mut f32)>(&mut self, f: &mut F) {
+		self.inner._params_mut(f);
+		self.next._params_mut(f);
+	}
+
+	fn _penalty(&self) -> f32 { self.inner._penalty() + self.next._penalty() }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::activation::Sigmoid;
+	use generic_array::{
+		arr,
+		typenum::{U2, U3},
+	};
+
+	type Net = InnerLayer<Sigmoid, U3, U2, U2, OutputLayer<Sigmoid, U2, U3>>;
+
+	fn change(before: &Net, after: &Net) -> GenericArray<f32, U2> {
+		after
+			.next
+			.bias
+			.iter()
+			.zip(&before.next.bias)
+			.map(|(a, b)| a - b)
+			.collect()
+	}
+
+	#[test]
+	fn batch_applies_average() {
+		let first = (arr![f32; 0.3, 0.8], arr![f32; 1., 0.]);
+		let second = (arr![f32; 0.9, 0.1], arr![f32; 0., 1.]);
+		let net = Net::push(OutputLayer::new());
+
+		// accumulating alone does not change the weights
+		let mut batched = net.clone();
+		batched.accumulate(&first.0, &first.1);
+		batched.accumulate(&second.0, &second.1);
+		assert_eq!(batched.inner.weights, net.inner.weights);
+		batched.apply_batch(2, 0.5);
+
+		// each sample on its own, starting from the same weights
+		let mut single_first = net.clone();
+		single_first.backprop(&first.0, &first.1, 0.25);
+		let mut single_second = net.clone();
+		single_second.backprop(&second.0, &second.1, 0.25);
+
+		let expected = change(&net, &single_first)
+			.iter()
+			.zip(&change(&net, &single_second))
+			.map(|(a, b)| a + b)
+			.collect::<GenericArray<f32, U2>>();
+		for (batched, expected) in change(&net, &batched).iter().zip(&expected) {
+			assert!((batched - expected).abs() < 1e-6);
+		}
+	}
+
+	#[test]
+	fn seeded_init_is_reproducible() {
+		let weights = |net: &Net| format!("{:?}", net);
+		assert_eq!(
+			weights(&Net::push(OutputLayer::new())),
+			weights(&Net::push(OutputLayer::new()))
+		);
+
+		let seeded = |seed| {
+			let mut rng = Pcg32::seed_from_u64(seed);
+			Net::push_with_rng(OutputLayer::new_with_rng(&mut rng), &mut rng)
+		};
+		assert_eq!(weights(&seeded(1)), weights(&seeded(1)));
+		assert_ne!(weights(&seeded(1)), weights(&seeded(2)));
+
+		// sharing one rng gives layers of the same shape different weights
+		let mut rng = Pcg32::seed_from_u64(1);
+		let first = OutputLayer::<Sigmoid, U2, U3>::new_with_rng(&mut rng);
+		let second = OutputLayer::<Sigmoid, U2, U3>::new_with_rng(&mut rng);
+		assert_ne!(first.weights, second.weights);
 	}
 }
@@ -27,15 +27,43 @@
 //! Also it was a fun challenge and actually worked out :)
 //!
 //! Check the examples directory for some simple networks to get started.
+//!
+//! ## Features
+//! - `std`: enables the `datasets` module for loading training data from disk
 
 #[macro_use]
 extern crate serde_derive;
 
+#[cfg(any(test, feature = "std"))]
+#[macro_use]
+extern crate std;
+
 extern crate generic_array;
 
+#[macro_use]
+mod macros;
+
 pub mod activation;
-//pub mod convolution;
+pub mod autoencoder;
+pub mod conv1d;
+pub mod convolution;
+#[cfg(feature = "std")]
+pub mod datasets;
+pub mod dense;
+pub mod depthwise;
+pub mod dropout;
+pub mod gradcheck;
+pub mod initializer;
 pub mod layers;
-//pub mod loss;
+pub mod loss;
+pub mod normalization;
+pub mod optimizer;
+pub mod pooling;
 pub mod prelude;
+pub mod regularizer;
+pub mod schedule;
 pub mod softmax;
+#[cfg(test)]
+mod testing;
+pub mod trainer;
+pub mod upsampling;
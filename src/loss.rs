@@ -1,23 +1,217 @@
-use crate::layers::AL;
-use core::marker::PhantomData;
-use generic_array::GenericArray;
+//! Losses for use in the final [layer](crate::layers) of a network
+//!
+//! A loss rates how far the prediction of a network is off from the correct output. Training
+//! follows its gradient downhill.
+//!
+//! Just like [activations](crate::activation) the Enums in this module are empty and therefore
+//! not initializable. They are only used as type parameters.
+use core::fmt::Debug;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// keeps logarithms and divisions away from 0
+const EPSILON: f32 = 1e-7;
 
 //todo: maybe make loss generic over activations
 // and require some extra features
 // like sum(prediction) == 1
 // or prediction.all > 0
-trait Loss<N: AL> {
-    fn loss(prediction: &mut GenericArray<f32, N>, reality: &GenericArray<f32, N>);
+pub trait Loss: Debug + Serialize + DeserializeOwned + Clone {
+	/// Rates the whole prediction with a single number, lower is better.
+	///
+	/// prediction and reality have the same length.
+	fn loss(prediction: &[f32], reality: &[f32]) -> f32;
+
+	/// Writes the derivate of [loss](Loss::loss) for each of the predictions into gradient.
+	///
+	/// This points uphill, so the error that gets propagated back is the negated gradient.
+	fn gradient(prediction: &[f32], reality: &[f32], gradient: &mut [f32]);
+}
+
+/// Mean squared error.
+///
+/// mean((prediction - reality)^2)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Mse {}
+
+impl Loss for Mse {
+	fn loss(prediction: &[f32], reality: &[f32]) -> f32 {
+		let sum: f32 = prediction
+			.iter()
+			.zip(reality)
+			.map(|(pre, real)| (pre - real) * (pre - real))
+			.sum();
+		sum / prediction.len() as f32
+	}
+
+	fn gradient(prediction: &[f32], reality: &[f32], gradient: &mut [f32]) {
+		let scale = 2. / prediction.len() as f32;
+		for ((gradient, pre), real) in gradient.iter_mut().zip(prediction).zip(reality) {
+			*gradient = scale * (pre - real);
+		}
+	}
+}
+
+/// Mean absolute error, less sensitive to outliers than [Mse].
+///
+/// mean(|prediction - reality|)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Mae {}
+
+impl Loss for Mae {
+	fn loss(prediction: &[f32], reality: &[f32]) -> f32 {
+		let sum: f32 = prediction
+			.iter()
+			.zip(reality)
+			.map(|(pre, real)| (pre - real).abs())
+			.sum();
+		sum / prediction.len() as f32
+	}
+
+	fn gradient(prediction: &[f32], reality: &[f32], gradient: &mut [f32]) {
+		let scale = 1. / prediction.len() as f32;
+		for ((gradient, pre), real) in gradient.iter_mut().zip(prediction).zip(reality) {
+			let diff = pre - real;
+			*gradient = if diff == 0. {
+				0.
+			} else {
+				scale * diff.signum()
+			};
+		}
+	}
 }
 
+/// Huber loss with a delta of 1.
+///
+/// Quadratic like [Mse] for small errors and linear like [Mae] for big ones.
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Linear<N: AL>(PhantomData<N>);
-
-impl<N: AL> Loss<N> for Linear<N> {
-    fn loss(prediction: &mut GenericArray<f32, N>, reality: &GenericArray<f32, N>) {
-        prediction.iter_mut().zip(reality).for_each(|(pre, &real)| {
-            // subtract the output from the expected output to get the error
-            *pre = real - *pre;
-        });
-    }
+pub enum Huber {}
+
+impl Huber {
+	const DELTA: f32 = 1.;
+}
+
+impl Loss for Huber {
+	fn loss(prediction: &[f32], reality: &[f32]) -> f32 {
+		let sum: f32 = prediction
+			.iter()
+			.zip(reality)
+			.map(|(pre, real)| {
+				let diff = (pre - real).abs();
+				if diff <= Self::DELTA {
+					0.5 * diff * diff
+				} else {
+					Self::DELTA * (diff - 0.5 * Self::DELTA)
+				}
+			})
+			.sum();
+		sum / prediction.len() as f32
+	}
+
+	fn gradient(prediction: &[f32], reality: &[f32], gradient: &mut [f32]) {
+		let scale = 1. / prediction.len() as f32;
+		for ((gradient, pre), real) in gradient.iter_mut().zip(prediction).zip(reality) {
+			*gradient = scale * (pre - real).clamp(-Self::DELTA, Self::DELTA);
+		}
+	}
+}
+
+/// Binary cross-entropy, for independent yes/no outputs between 0 and 1, like the ones of a
+/// [Sigmoid](crate::activation::Sigmoid).
+///
+/// mean(-(reality * ln(prediction) + (1 - reality) * ln(1 - prediction)))
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum BinaryCrossEntropy {}
+
+impl Loss for BinaryCrossEntropy {
+	fn loss(prediction: &[f32], reality: &[f32]) -> f32 {
+		let sum: f32 = prediction
+			.iter()
+			.zip(reality)
+			.map(|(pre, real)| {
+				let pre = pre.clamp(EPSILON, 1. - EPSILON);
+				-(real * pre.ln() + (1. - real) * (1. - pre).ln())
+			})
+			.sum();
+		sum / prediction.len() as f32
+	}
+
+	fn gradient(prediction: &[f32], reality: &[f32], gradient: &mut [f32]) {
+		let scale = 1. / prediction.len() as f32;
+		for ((gradient, pre), real) in gradient.iter_mut().zip(prediction).zip(reality) {
+			let pre = pre.clamp(EPSILON, 1. - EPSILON);
+			*gradient = scale * (pre - real) / (pre * (1. - pre));
+		}
+	}
+}
+
+/// Categorical cross-entropy, for a probability distribution over classes like the output of a
+/// [SoftMax](crate::softmax::SoftMax) layer.
+///
+/// -sum(reality * ln(prediction))
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum CategoricalCrossEntropy {}
+
+impl Loss for CategoricalCrossEntropy {
+	fn loss(prediction: &[f32], reality: &[f32]) -> f32 {
+		prediction
+			.iter()
+			.zip(reality)
+			.map(|(pre, real)| -real * pre.max(EPSILON).ln())
+			.sum()
+	}
+
+	fn gradient(prediction: &[f32], reality: &[f32], gradient: &mut [f32]) {
+		for ((gradient, pre), real) in gradient.iter_mut().zip(prediction).zip(reality) {
+			*gradient = -real / pre.max(EPSILON);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use generic_array::{arr, typenum::U3, GenericArray};
+
+	fn check_gradient<L: Loss>(prediction: GenericArray<f32, U3>, reality: GenericArray<f32, U3>) {
+		let mut gradient = [0.; 3];
+		L::gradient(&prediction, &reality, &mut gradient);
+		let epsilon = 1e-3;
+		for i in 0..3 {
+			let mut up = prediction;
+			up[i] += epsilon;
+			let mut down = prediction;
+			down[i] -= epsilon;
+			let numeric = (L::loss(&up, &reality) - L::loss(&down, &reality)) / (2. * epsilon);
+			assert!(
+				(numeric - gradient[i]).abs() < 1e-2,
+				"{:?}: {} != {}",
+				L::loss(&prediction, &reality),
+				numeric,
+				gradient[i]
+			);
+		}
+	}
+
+	#[test]
+	fn gradients() {
+		let prediction = arr![f32; 0.2, 0.7, 0.1];
+		let reality = arr![f32; 0., 1., 0.];
+		check_gradient::<Mse>(prediction, reality);
+		check_gradient::<Mae>(prediction, reality);
+		check_gradient::<BinaryCrossEntropy>(prediction, reality);
+		check_gradient::<CategoricalCrossEntropy>(prediction, reality);
+		check_gradient::<Huber>(prediction, reality);
+		// outside of the quadratic part
+		check_gradient::<Huber>(arr![f32; 3., -2., 0.5], reality);
+	}
+
+	#[test]
+	fn perfect_prediction() {
+		let reality = arr![f32; 0., 1., 0.];
+		assert_eq!(Mse::loss(&reality, &reality), 0.);
+		assert_eq!(Mae::loss(&reality, &reality), 0.);
+		assert_eq!(Huber::loss(&reality, &reality), 0.);
+		assert!(BinaryCrossEntropy::loss(&reality, &reality) < 1e-5);
+		assert!(CategoricalCrossEntropy::loss(&reality, &reality) < 1e-5);
+	}
 }
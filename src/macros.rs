@@ -0,0 +1,208 @@
+//! The [network!](crate::network) macro.
+
+/// Builds a stack of [layers](crate::layers) from the input down to the final layer.
+///
+/// Starting with the number of inputs, every following entry is a layer:
+/// - `Dense<Activation, Neurons>` is a fully connected layer, the final one may also name its
+///   [Loss](crate::loss::Loss) as in `Dense<Sigmoid, U1, BinaryCrossEntropy>`
+/// - `SoftMax<Neurons>` is a fully connected layer with a
+///   [SoftMax](crate::activation::SoftMax) activation, the final one uses categorical
+///   cross-entropy unless it names another loss
+/// - `Dropout<Percent>` drops that percentage of the outputs of the layer above while training,
+///   see [Dropout](crate::dropout::Dropout)
+/// - `BatchNorm` and `LayerNorm` normalize the outputs of the layer above, see
+///   [normalization](crate::normalization)
+///
+/// Used as an expression it creates the network, drawing all weights from one rng. That is
+/// [default_rng](crate::layers::default_rng) unless another one is given with `with rng;`.
+///
+/// ```
+/// use dumbnet::{activation::Sigmoid, network, prelude::Network};
+/// use generic_array::{arr, typenum::{U1, U2, U4}};
+///
+/// let network = network![in U2 => Dense<Sigmoid, U4> => Dense<Sigmoid, U4> => Dense<Sigmoid, U1>];
+/// let output = network.calculate(&arr![f32; 0.2, 0.3]);
+/// ```
+///
+/// Used as an item it defines a type alias instead, for struct fields or deserialisation. Such
+/// networks can be created with `default()`.
+///
+/// ```
+/// use dumbnet::{activation::ReLu, network};
+/// use generic_array::typenum::{U10, U16, U784};
+///
+/// network!(pub type Digits = in U784 => Dense<ReLu, U16> => SoftMax<U10>);
+///
+/// struct Reader(Digits);
+///
+/// let reader = Reader(Digits::default());
+/// ```
+#[macro_export]
+macro_rules! network {
+	($(#[$meta:meta])* $vis:vis type $name:ident = in $input:ty => $($layers:tt)+) => {
+		$(#[$meta])*
+		$vis type $name = $crate::__network_type!($input; $($layers)+);
+	};
+	(in $input:ty => $($layers:tt)+) => {{
+		let mut rng = $crate::layers::default_rng();
+		$crate::network!(with &mut rng; in $input => $($layers)+)
+	}};
+	(with $rng:expr; in $input:ty => $($layers:tt)+) => {{
+		let rng = $rng;
+		$crate::__network_new!(rng; $input; $($layers)+)
+	}};
+}
+
+/// the type of the layers, used by [network!](crate::network)
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __network_type {
+	($input:ty; Dense<$a:ty, $n:ty> => $($rest:tt)+) => {
+		$crate::layers::InnerLayer<
+			$a,
+			$n,
+			$input,
+			$crate::__network_final!($($rest)+),
+			$crate::__network_type!($n; $($rest)+),
+		>
+	};
+	($input:ty; SoftMax<$n:ty> => $($rest:tt)+) => {
+		$crate::layers::InnerLayer<
+			$crate::activation::SoftMax,
+			$n,
+			$input,
+			$crate::__network_final!($($rest)+),
+			$crate::__network_type!($n; $($rest)+),
+		>
+	};
+	($input:ty; Dropout<$p:ty> => $($rest:tt)+) => {
+		$crate::dropout::Dropout<
+			$p,
+			$input,
+			$crate::__network_final!($($rest)+),
+			$crate::__network_type!($input; $($rest)+),
+		>
+	};
+	($input:ty; BatchNorm => $($rest:tt)+) => {
+		$crate::normalization::BatchNorm<
+			$input,
+			$crate::__network_final!($($rest)+),
+			$crate::__network_type!($input; $($rest)+),
+		>
+	};
+	($input:ty; LayerNorm => $($rest:tt)+) => {
+		$crate::normalization::LayerNorm<
+			$input,
+			$crate::__network_final!($($rest)+),
+			$crate::__network_type!($input; $($rest)+),
+		>
+	};
+	($input:ty; Dense<$a:ty, $n:ty $(, $l:ty)?>) => {
+		$crate::layers::OutputLayer<$a, $n, $input $(, $l)?>
+	};
+	($input:ty; SoftMax<$n:ty $(, $l:ty)?>) => {
+		$crate::softmax::SoftMax<$n, $input $(, $l)?>
+	};
+}
+
+/// the number of outputs of the final layer, used by [network!](crate::network)
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __network_final {
+	(Dense<$a:ty, $n:ty> => $($rest:tt)+) => { $crate::__network_final!($($rest)+) };
+	(SoftMax<$n:ty> => $($rest:tt)+) => { $crate::__network_final!($($rest)+) };
+	(Dropout<$p:ty> => $($rest:tt)+) => { $crate::__network_final!($($rest)+) };
+	(BatchNorm => $($rest:tt)+) => { $crate::__network_final!($($rest)+) };
+	(LayerNorm => $($rest:tt)+) => { $crate::__network_final!($($rest)+) };
+	(Dense<$a:ty, $n:ty $(, $l:ty)?>) => { $n };
+	(SoftMax<$n:ty $(, $l:ty)?>) => { $n };
+}
+
+/// creates the layers from the final one up, used by [network!](crate::network)
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __network_new {
+	($rng:ident; $input:ty; Dense<$a:ty, $n:ty> => $($rest:tt)+) => {{
+		let next = $crate::__network_new!($rng; $n; $($rest)+);
+		<$crate::__network_type!($input; Dense<$a, $n> => $($rest)+)>::push_with_rng(next, $rng)
+	}};
+	($rng:ident; $input:ty; SoftMax<$n:ty> => $($rest:tt)+) => {{
+		let next = $crate::__network_new!($rng; $n; $($rest)+);
+		<$crate::__network_type!($input; SoftMax<$n> => $($rest)+)>::push_with_rng(next, $rng)
+	}};
+	($rng:ident; $input:ty; Dropout<$p:ty> => $($rest:tt)+) => {{
+		let next = $crate::__network_new!($rng; $input; $($rest)+);
+		<$crate::__network_type!($input; Dropout<$p> => $($rest)+)>::push_with_rng(next, $rng)
+	}};
+	($rng:ident; $input:ty; BatchNorm => $($rest:tt)+) => {{
+		let next = $crate::__network_new!($rng; $input; $($rest)+);
+		<$crate::__network_type!($input; BatchNorm => $($rest)+)>::push(next)
+	}};
+	($rng:ident; $input:ty; LayerNorm => $($rest:tt)+) => {{
+		let next = $crate::__network_new!($rng; $input; $($rest)+);
+		<$crate::__network_type!($input; LayerNorm => $($rest)+)>::push(next)
+	}};
+	($rng:ident; $input:ty; $($layer:tt)+) => {
+		<$crate::__network_type!($input; $($layer)+)>::new_with_rng($rng)
+	};
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		activation::{self, Sigmoid},
+		dropout::Dropout,
+		layers::{default_rng, InnerLayer, Network, OutputLayer},
+		loss::BinaryCrossEntropy,
+		normalization::LayerNorm,
+		softmax::SoftMax,
+	};
+	use generic_array::typenum::{U1, U2, U20, U3, U4};
+
+	network!(type Xor = in U2 => Dense<Sigmoid, U4> => Dense<Sigmoid, U1, BinaryCrossEntropy>);
+
+	#[test]
+	fn expands_to_layers() {
+		let network: InnerLayer<
+			Sigmoid,
+			U4,
+			U2,
+			U1,
+			OutputLayer<Sigmoid, U1, U4, BinaryCrossEntropy>,
+		> = network![in U2 => Dense<Sigmoid, U4> => Dense<Sigmoid, U1, BinaryCrossEntropy>];
+		let _: Xor = network;
+
+		let _: InnerLayer<
+			activation::SoftMax,
+			U3,
+			U2,
+			U4,
+			InnerLayer<Sigmoid, U4, U3, U4, SoftMax<U4, U4>>,
+		> = network![in U2 => SoftMax<U3> => Dense<Sigmoid, U4> => SoftMax<U4>];
+
+		let _: InnerLayer<Sigmoid, U4, U2, U1, Dropout<U20, U4, U1, OutputLayer<Sigmoid, U1, U4>>> =
+			network![in U2 => Dense<Sigmoid, U4> => Dropout<U20> => Dense<Sigmoid, U1>];
+
+		let _: InnerLayer<Sigmoid, U4, U2, U1, LayerNorm<U4, U1, OutputLayer<Sigmoid, U1, U4>>> =
+			network![in U2 => Dense<Sigmoid, U4> => LayerNorm => Dense<Sigmoid, U1>];
+	}
+
+	#[test]
+	fn shares_the_rng() {
+		let mut rng = default_rng();
+		let next = OutputLayer::<Sigmoid, U1, U4>::new_with_rng(&mut rng);
+		let expected = InnerLayer::<Sigmoid, U4, U2, U1, _>::push_with_rng(next, &mut rng);
+
+		let built: Xor =
+			network![in U2 => Dense<Sigmoid, U4> => Dense<Sigmoid, U1, BinaryCrossEntropy>];
+		let input = generic_array::arr![f32; 0.3, -0.6];
+		assert_eq!(expected.calculate(&input), built.calculate(&input));
+
+		let mut rng = default_rng();
+		let with_rng: Xor = network![
+			with &mut rng;
+			in U2 => Dense<Sigmoid, U4> => Dense<Sigmoid, U1, BinaryCrossEntropy>
+		];
+		assert_eq!(format!("{:?}", with_rng), format!("{:?}", built));
+	}
+}
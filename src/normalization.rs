@@ -0,0 +1,672 @@
+//! Normalization layers rescale the outputs of the layer above them to a mean of 0 and a variance
+//! of 1, then shift and scale them by a learned beta and gamma.
+//!
+//! That keeps deep stacks of saturating activations like [Sigmoid](crate::activation::Sigmoid) in
+//! the range where their derivates are large, so the error still reaches the upper layers.
+//!
+//! Just like [Dropout](crate::dropout::Dropout) they are pushed between two layers, or put between
+//! them in the [network!](crate::network) macro:
+//!
+//! ```
+//! use dumbnet::{activation::Sigmoid, network};
+//! use generic_array::typenum::{U1, U2, U8};
+//!
+//! let network = network![in U2 => Dense<Sigmoid, U8> => LayerNorm => Dense<Sigmoid, U1>];
+//! ```
+use crate::{
+	layers::{apply_deltas, map_batch, params_mut, sealed, Differentiable, AL},
+	optimizer::{Optimizer, Sgd},
+};
+use generic_array::GenericArray;
+
+/// keeps the normalization from dividing by 0
+const EPSILON: f32 = 1e-5;
+
+/// The learned gamma and beta of a normalization layer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "N: AL")]
+struct Affine<N: AL, O: Optimizer> {
+	gamma: GenericArray<f32, N>,
+	beta: GenericArray<f32, N>,
+	gamma_state: O::State<GenericArray<f32, N>>,
+	beta_state: O::State<GenericArray<f32, N>>,
+	// deltas of the current batch
+	#[serde(skip)]
+	gamma_deltas: GenericArray<f32, N>,
+	#[serde(skip)]
+	beta_deltas: GenericArray<f32, N>,
+}
+
+impl<N: AL, O: Optimizer> Affine<N, O> {
+	/// starts out passing the normalized values through unchanged
+	fn new() -> Self {
+		Self {
+			gamma: (0..N::to_usize()).map(|_| 1.).collect(),
+			beta: GenericArray::default(),
+			gamma_state: Default::default(),
+			beta_state: Default::default(),
+			gamma_deltas: GenericArray::default(),
+			beta_deltas: GenericArray::default(),
+		}
+	}
+
+	fn forward(&self, normalized: &GenericArray<f32, N>) -> GenericArray<f32, N> {
+		normalized
+			.iter()
+			.zip(self.gamma.iter().zip(&self.beta))
+			.map(|(x, (gamma, beta))| gamma * x + beta)
+			.collect()
+	}
+
+	/// stores the deltas of gamma and beta and returns the error of the normalized values
+	fn backward(
+		&mut self,
+		normalized: &GenericArray<f32, N>,
+		error: &GenericArray<f32, N>,
+	) -> GenericArray<f32, N> {
+		for (i, error) in error.iter().enumerate() {
+			self.gamma_deltas[i] += error * normalized[i];
+			self.beta_deltas[i] += error;
+		}
+		error.iter().zip(&self.gamma).map(|(e, g)| e * g).collect()
+	}
+
+	fn apply_deltas(&mut self, batch_size: usize, speed: f32) {
+		apply_deltas::<O, _>(
+			&mut self.gamma,
+			&mut self.gamma_state,
+			&mut self.gamma_deltas,
+			batch_size,
+			speed,
+		);
+		apply_deltas::<O, _>(
+			&mut self.beta,
+			&mut self.beta_state,
+			&mut self.beta_deltas,
+			batch_size,
+			speed,
+		);
+	}
+
+	fn params_mut<F: FnMut(&mut f32, &mut f32)>(&mut self, f: &mut F) {
+		params_mut(&mut self.gamma, &mut self.gamma_deltas, f);
+		params_mut(&mut self.beta, &mut self.beta_deltas, f);
+	}
+}
+
+/// Normalizes each of its N inputs by its mean and variance across the examples of a batch.
+///
+/// Trained on whole batches, with
+/// [accumulate_batch](crate::layers::Network::accumulate_batch),
+/// [backprop_batch](crate::layers::Network::backprop_batch) or
+/// [teach_batches](crate::layers::Network::teach_batches), it normalizes with the statistics of
+/// the batch and carries the error back through them. Every layer above it passes the batch on
+/// as a whole.
+///
+/// The batches also update running estimates of the mean and variance once they are applied,
+/// which [calculate](crate::layers::Network::calculate) uses, since there is no batch to take
+/// statistics of. They start out as a mean of 0 and a variance of 1, then are the statistics of
+/// all batches so far, until those are more than about a hundred examples and older ones start to
+/// fade out. They get serialized along with the layer.
+///
+/// Trained one example at a time, like by [teach_epoch](crate::layers::Network::teach_epoch) or a
+/// [Trainer](crate::trainer::Trainer), it normalizes with the running estimates instead and
+/// leaves them as they are.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "N: AL, FinalOut: AL")]
+pub struct BatchNorm<N: AL, FinalOut: AL, Next: Differentiable<N, FinalOut>, O: Optimizer = Sgd> {
+	affine: Affine<N, O>,
+	mean: GenericArray<f32, N>,
+	variance: GenericArray<f32, N>,
+	// number of examples the running statistics are made of
+	seen: u64,
+	// sums of the inputs and their squares of the batches of the current update
+	#[serde(skip)]
+	sum: GenericArray<f32, N>,
+	#[serde(skip)]
+	squares: GenericArray<f32, N>,
+	#[serde(skip)]
+	count: usize,
+	next: Next,
+	phantom: core::marker::PhantomData<FinalOut>,
+}
+
+impl<N: AL, FinalOut: AL, Next: Differentiable<N, FinalOut>, O: Optimizer>
+	BatchNorm<N, FinalOut, Next, O>
+{
+	/// how much of the running statistics is at least kept per example
+	const MOMENTUM: f32 = 0.99;
+
+	/// Pushes this layer on top of an existing layer.
+	pub fn push(next: Next) -> Self {
+		Self {
+			affine: Affine::new(),
+			mean: GenericArray::default(),
+			variance: (0..N::to_usize()).map(|_| 1.).collect(),
+			seen: 0,
+			sum: GenericArray::default(),
+			squares: GenericArray::default(),
+			count: 0,
+			next,
+			phantom: core::marker::PhantomData,
+		}
+	}
+
+	/// the running mean of every input
+	pub fn mean(&self) -> &GenericArray<f32, N> { &self.mean }
+
+	/// the running variance of every input
+	pub fn variance(&self) -> &GenericArray<f32, N> { &self.variance }
+
+	/// normalizes inputs using the running statistics
+	fn normalize(&self, inputs: &GenericArray<f32, N>) -> GenericArray<f32, N> {
+		inputs
+			.iter()
+			.zip(self.mean.iter().zip(&self.variance))
+			.map(|(x, (mean, variance))| (x - mean) / (variance + EPSILON).sqrt())
+			.collect()
+	}
+
+	/// the inputs normalized by the statistics of the batch, and the standard deviation of each
+	/// input
+	fn normalize_batch<const B: usize>(
+		inputs: &[GenericArray<f32, N>; B],
+	) -> ([GenericArray<f32, N>; B], GenericArray<f32, N>) {
+		let n = B as f32;
+		let mut mean = GenericArray::<f32, N>::default();
+		let mut variance = GenericArray::<f32, N>::default();
+		for inputs in inputs {
+			mean.iter_mut().zip(inputs).for_each(|(m, x)| *m += x / n);
+		}
+		for inputs in inputs {
+			for ((v, x), m) in variance.iter_mut().zip(inputs).zip(&mean) {
+				*v += (x - m) * (x - m) / n;
+			}
+		}
+		let deviation: GenericArray<f32, N> =
+			variance.iter().map(|v| (v + EPSILON).sqrt()).collect();
+		let normalized = inputs.each_ref().map(|inputs| {
+			inputs
+				.iter()
+				.zip(mean.iter().zip(&deviation))
+				.map(|(x, (mean, deviation))| (x - mean) / deviation)
+				.collect()
+		});
+		(normalized, deviation)
+	}
+
+	/// moves the running statistics towards the ones of the batches since the last update
+	fn update_statistics(&mut self) {
+		// a plain average of everything seen until the momentum takes over, so the initial
+		// statistics do not linger
+		let total = self.seen + self.count as u64;
+		let keep = Self::MOMENTUM
+			.powi(self.count as i32)
+			.min(self.seen as f32 / total as f32);
+		self.seen = total;
+		for i in 0..N::to_usize() {
+			let square = self.variance[i] + self.mean[i] * self.mean[i];
+			let batch_mean = self.sum[i] / self.count as f32;
+			let batch_square = self.squares[i] / self.count as f32;
+			self.mean[i] = keep * self.mean[i] + (1. - keep) * batch_mean;
+			let square = keep * square + (1. - keep) * batch_square;
+			self.variance[i] = (square - self.mean[i] * self.mean[i]).max(0.);
+		}
+		self.sum = GenericArray::default();
+		self.squares = GenericArray::default();
+		self.count = 0;
+	}
+}
+
+impl<N: AL, FinalOut: AL, Next: Differentiable<N, FinalOut> + Default, O: Optimizer> Default
+	for BatchNorm<N, FinalOut, Next, O>
+{
+	fn default() -> Self { Self::push(Next::default()) }
+}
+
+impl<N: AL, FinalOut: AL, Next: Differentiable<N, FinalOut>, O: Optimizer> sealed::Sealed
+	for BatchNorm<N, FinalOut, Next, O>
+{
+}
+
+impl<N: AL, FinalOut: AL, Next: Differentiable<N, FinalOut>, O: Optimizer>
+	Differentiable<N, FinalOut> for BatchNorm<N, FinalOut, Next, O>
+{
+	fn _calculate(&self, inputs: &GenericArray<f32, N>) -> GenericArray<f32, FinalOut> {
+		self.next
+			._calculate(&self.affine.forward(&self.normalize(inputs)))
+	}
+
+	fn _loss(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> f32 {
+		Next::_loss(output, correct_output)
+	}
+
+	fn _error(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> (GenericArray<f32, FinalOut>, f32) {
+		Next::_error(output, correct_output)
+	}
+
+	fn _accumulate_with<F>(
+		&mut self,
+		inputs: &GenericArray<f32, N>,
+		objective: F,
+	) -> (GenericArray<f32, N>, f32)
+	where
+		F: FnOnce(&GenericArray<f32, FinalOut>) -> (GenericArray<f32, FinalOut>, f32),
+	{
+		let normalized = self.normalize(inputs);
+		let output = self.affine.forward(&normalized);
+		let (error, loss) = self.next._accumulate_with(&output, objective);
+		let error = self.affine.backward(&normalized, &error);
+		// the running statistics do not depend on this example, so each input only gets scaled
+		let error = error
+			.iter()
+			.zip(&self.variance)
+			.map(|(e, variance)| e / (variance + EPSILON).sqrt())
+			.collect();
+		(error, loss)
+	}
+
+	fn _accumulate_batch<const B: usize, F>(
+		&mut self,
+		inputs: &[GenericArray<f32, N>; B],
+		objective: F,
+	) -> ([GenericArray<f32, N>; B], f32)
+	where
+		F: FnOnce(&[GenericArray<f32, FinalOut>; B]) -> ([GenericArray<f32, FinalOut>; B], f32),
+	{
+		for inputs in inputs {
+			for (i, x) in inputs.iter().enumerate() {
+				self.sum[i] += x;
+				self.squares[i] += x * x;
+			}
+		}
+		self.count += B;
+
+		let (normalized, deviation) = Self::normalize_batch(inputs);
+		let outputs = normalized
+			.each_ref()
+			.map(|normalized| self.affine.forward(normalized));
+		let (errors, loss) = self.next._accumulate_batch(&outputs, objective);
+		let errors = map_batch(errors, |i, error| {
+			self.affine.backward(&normalized[i], &error)
+		});
+
+		// every example moves the mean and the variance, which moves all normalized values
+		let n = B as f32;
+		let mut mean_error = GenericArray::<f32, N>::default();
+		let mut mean_scaled_error = GenericArray::<f32, N>::default();
+		for (error, normalized) in errors.iter().zip(&normalized) {
+			for i in 0..N::to_usize() {
+				mean_error[i] += error[i] / n;
+				mean_scaled_error[i] += error[i] * normalized[i] / n;
+			}
+		}
+		let errors = map_batch(errors, |example, error| {
+			(0..N::to_usize())
+				.map(|i| {
+					(error[i] - mean_error[i] - normalized[example][i] * mean_scaled_error[i])
+						/ deviation[i]
+				})
+				.collect()
+		});
+		(errors, loss)
+	}
+
+	fn _apply_deltas(&mut self, batch_size: usize, speed: f32) {
+		self.affine.apply_deltas(batch_size, speed);
+		if self.count > 0 {
+			self.update_statistics();
+		}
+		self.next._apply_deltas(batch_size, speed);
+	}
+
+	fn _params_mut<F: FnMut(&mut f32, &mut f32)>(&mut self, f: &mut F) {
+		self.affine.params_mut(f);
+		self.next._params_mut(f);
+	}
+
+	fn _penalty(&self) -> f32 { self.next._penalty() }
+}
+
+/// Normalizes its N inputs by their mean and variance within each single example.
+///
+/// Unlike [BatchNorm] it does not keep any statistics, so it works the same during training and
+/// afterwards, and on single examples as well as on batches.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "N: AL, FinalOut: AL")]
+pub struct LayerNorm<N: AL, FinalOut: AL, Next: Differentiable<N, FinalOut>, O: Optimizer = Sgd> {
+	affine: Affine<N, O>,
+	next: Next,
+	phantom: core::marker::PhantomData<FinalOut>,
+}
+
+impl<N: AL, FinalOut: AL, Next: Differentiable<N, FinalOut>, O: Optimizer>
+	LayerNorm<N, FinalOut, Next, O>
+{
+	/// Pushes this layer on top of an existing layer.
+	pub fn push(next: Next) -> Self {
+		Self {
+			affine: Affine::new(),
+			next,
+			phantom: core::marker::PhantomData,
+		}
+	}
+
+	/// the normalized inputs and their standard deviation
+	fn normalize(inputs: &GenericArray<f32, N>) -> (GenericArray<f32, N>, f32) {
+		let n = N::to_usize() as f32;
+		let mean = inputs.iter().sum::<f32>() / n;
+		let variance = inputs.iter().map(|x| (x - mean) * (x - mean)).sum::<f32>() / n;
+		let deviation = (variance + EPSILON).sqrt();
+		(
+			inputs.iter().map(|x| (x - mean) / deviation).collect(),
+			deviation,
+		)
+	}
+
+	/// stores the deltas of gamma and beta and returns the error of the inputs
+	fn backward(
+		&mut self,
+		normalized: &GenericArray<f32, N>,
+		deviation: f32,
+		error: &GenericArray<f32, N>,
+	) -> GenericArray<f32, N> {
+		let error = self.affine.backward(normalized, error);
+
+		// every input moves the mean and the variance, which moves all normalized values
+		let n = N::to_usize() as f32;
+		let mean_error = error.iter().sum::<f32>() / n;
+		let mean_scaled_error = error
+			.iter()
+			.zip(normalized)
+			.map(|(e, x)| e * x)
+			.sum::<f32>()
+			/ n;
+		error
+			.iter()
+			.zip(normalized)
+			.map(|(e, x)| (e - mean_error - x * mean_scaled_error) / deviation)
+			.collect()
+	}
+}
+
+impl<N: AL, FinalOut: AL, Next: Differentiable<N, FinalOut> + Default, O: Optimizer> Default
+	for LayerNorm<N, FinalOut, Next, O>
+{
+	fn default() -> Self { Self::push(Next::default()) }
+}
+
+impl<N: AL, FinalOut: AL, Next: Differentiable<N, FinalOut>, O: Optimizer> sealed::Sealed
+	for LayerNorm<N, FinalOut, Next, O>
+{
+}
+
+impl<N: AL, FinalOut: AL, Next: Differentiable<N, FinalOut>, O: Optimizer>
+	Differentiable<N, FinalOut> for LayerNorm<N, FinalOut, Next, O>
+{
+	fn _calculate(&self, inputs: &GenericArray<f32, N>) -> GenericArray<f32, FinalOut> {
+		self.next
+			._calculate(&self.affine.forward(&Self::normalize(inputs).0))
+	}
+
+	fn _loss(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> f32 {
+		Next::_loss(output, correct_output)
+	}
+
+	fn _error(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> (GenericArray<f32, FinalOut>, f32) {
+		Next::_error(output, correct_output)
+	}
+
+	fn _accumulate_with<F>(
+		&mut self,
+		inputs: &GenericArray<f32, N>,
+		objective: F,
+	) -> (GenericArray<f32, N>, f32)
+	where
+		F: FnOnce(&GenericArray<f32, FinalOut>) -> (GenericArray<f32, FinalOut>, f32),
+	{
+		let (normalized, deviation) = Self::normalize(inputs);
+		let output = self.affine.forward(&normalized);
+		let (error, loss) = self.next._accumulate_with(&output, objective);
+		(self.backward(&normalized, deviation, &error), loss)
+	}
+
+	fn _accumulate_batch<const B: usize, F>(
+		&mut self,
+		inputs: &[GenericArray<f32, N>; B],
+		objective: F,
+	) -> ([GenericArray<f32, N>; B], f32)
+	where
+		F: FnOnce(&[GenericArray<f32, FinalOut>; B]) -> ([GenericArray<f32, FinalOut>; B], f32),
+	{
+		let normalized = inputs.each_ref().map(Self::normalize);
+		let outputs = normalized
+			.each_ref()
+			.map(|(normalized, _)| self.affine.forward(normalized));
+		let (errors, loss) = self.next._accumulate_batch(&outputs, objective);
+		let errors = map_batch(errors, |i, error| {
+			let (normalized, deviation) = &normalized[i];
+			self.backward(normalized, *deviation, &error)
+		});
+		(errors, loss)
+	}
+
+	fn _apply_deltas(&mut self, batch_size: usize, speed: f32) {
+		self.affine.apply_deltas(batch_size, speed);
+		self.next._apply_deltas(batch_size, speed);
+	}
+
+	fn _params_mut<F: FnMut(&mut f32, &mut f32)>(&mut self, f: &mut F) {
+		self.affine.params_mut(f);
+		self.next._params_mut(f);
+	}
+
+	fn _penalty(&self) -> f32 { self.next._penalty() }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		activation::Sigmoid,
+		gradcheck::{check, check_params},
+		layers::{Network, OutputLayer},
+	};
+	use generic_array::{
+		arr,
+		typenum::{U1, U2, U4},
+	};
+
+	type Output = OutputLayer<Sigmoid, U2, U4>;
+
+	fn input() -> GenericArray<f32, U4> { arr![f32; 0.3, -1.2, 2.5, 0.8] }
+
+	fn batch() -> [GenericArray<f32, U4>; 3] {
+		[
+			input(),
+			arr![f32; 1., 0.5, -2., 0.],
+			arr![f32; -0.4, 0.9, 0.1, 1.5],
+		]
+	}
+
+	#[test]
+	fn gradients() {
+		let mut batch_norm = BatchNorm::<U4, U2, Output>::push(Output::new());
+		batch_norm.backprop_batch(&batch(), &[arr![f32; 1., 0.]; 3], 0.5);
+		let result = check(&batch_norm, &input(), &arr![f32; 0., 1.], 1e-2, 1e-2);
+		assert!(result.is_ok(), "{:?}", result);
+
+		let mut layer_norm = LayerNorm::<U4, U2, Output>::push(Output::new());
+		layer_norm.backprop(&input(), &arr![f32; 1., 0.], 0.5);
+		let result = check(&layer_norm, &input(), &arr![f32; 0., 1.], 1e-2, 1e-2);
+		assert!(result.is_ok(), "{:?}", result);
+	}
+
+	#[test]
+	fn batch_gradients() {
+		// the inputs are parameters too, with their errors as deltas
+		type Model<Net> = (Net, [GenericArray<f32, U4>; 3], [GenericArray<f32, U4>; 3]);
+		fn assert_batch_gradients<Net: Differentiable<U4, U2>>(network: Net) {
+			let correct = [arr![f32; 1., 0.], arr![f32; 0., 1.], arr![f32; 0.5, 0.5]];
+			let result = check_params(
+				&(network, batch(), batch()),
+				|(network, inputs, errors): &mut Model<Net>, f| {
+					network._params_mut(&mut |param, delta| f(param, delta));
+					for (inputs, errors) in inputs.iter_mut().zip(errors.iter_mut()) {
+						inputs.iter_mut().zip(errors).for_each(|(i, e)| f(i, e));
+					}
+				},
+				|(network, inputs, errors)| {
+					let (accumulated, _) = network.accumulate_batch(inputs, &correct);
+					*errors = accumulated;
+				},
+				// the deltas add up, so the loss does too
+				|(network, inputs, _)| network.clone().accumulate_batch(inputs, &correct).1 * 3.,
+				1e-2,
+				1e-2,
+			);
+			assert!(result.is_ok(), "{:?}", result);
+		}
+
+		assert_batch_gradients(BatchNorm::<U4, U2, Output>::push(Output::new()));
+		assert_batch_gradients(LayerNorm::<U4, U2, Output>::push(Output::new()));
+	}
+
+	#[test]
+	fn normalizes_batches() {
+		let (normalized, deviation) = BatchNorm::<U4, U2, Output>::normalize_batch(&batch());
+		for i in 0..4 {
+			let mean = normalized.iter().map(|n| n[i]).sum::<f32>() / 3.;
+			let variance = normalized.iter().map(|n| n[i] * n[i]).sum::<f32>() / 3.;
+			assert!(mean.abs() < 1e-6, "{}", mean);
+			assert!((variance - 1.).abs() < 1e-3, "{}", variance);
+		}
+		// the first input is 0.3, 1 and -0.4
+		let variance = (0.3f32 * 0.3 + 1. + 0.4 * 0.4) / 3. - 0.3 * 0.3;
+		assert!((deviation[0] - variance.sqrt()).abs() < 1e-3);
+	}
+
+	#[test]
+	fn normalizes_single_examples() {
+		let (normalized, _) = LayerNorm::<U4, U2, Output>::normalize(&input());
+		let mean = normalized.iter().sum::<f32>() / 4.;
+		let variance = normalized.iter().map(|x| x * x).sum::<f32>() / 4.;
+		assert!(mean.abs() < 1e-6);
+		assert!((variance - 1.).abs() < 1e-3);
+	}
+
+	#[test]
+	fn starts_with_the_statistics_of_the_first_batch() {
+		let mut batch_norm =
+			BatchNorm::<U2, U1, OutputLayer<Sigmoid, U1, U2>>::push(OutputLayer::new());
+		let correct = [arr![f32; 0.5]; 4];
+		let batch = [5., 1., 5., 1.].map(|x| arr![f32; x, -1.]);
+		batch_norm.backprop_batch(&batch, &correct, 0.);
+		assert_eq!(batch_norm.mean(), &arr![f32; 3., -1.]);
+		assert_eq!(batch_norm.variance(), &arr![f32; 4., 0.]);
+
+		// the second batch counts as much as the first
+		batch_norm.backprop_batch(&[arr![f32; 9., -1.]; 4], &correct, 0.);
+		assert_eq!(batch_norm.mean()[0], 6.);
+	}
+
+	#[test]
+	fn tracks_statistics() {
+		let mut batch_norm =
+			BatchNorm::<U2, U1, OutputLayer<Sigmoid, U1, U2>>::push(OutputLayer::new());
+		let correct = [arr![f32; 0.5]; 2];
+		for _ in 0..1000 {
+			// the first input is 3 ± 2, the second always -1
+			batch_norm.backprop_batch(&[arr![f32; 5., -1.], arr![f32; 1., -1.]], &correct, 0.);
+		}
+		assert!(
+			(batch_norm.mean()[0] - 3.).abs() < 0.1,
+			"{:?}",
+			batch_norm.mean()
+		);
+		assert!(
+			(batch_norm.variance()[0] - 4.).abs() < 0.2,
+			"{:?}",
+			batch_norm.variance()
+		);
+		assert!((batch_norm.mean()[1] + 1.).abs() < 1e-3);
+		assert!(batch_norm.variance()[1] < 1e-3);
+
+		// single examples use the statistics without changing them
+		let mean = *batch_norm.mean();
+		batch_norm.backprop(&arr![f32; 100., 100.], &arr![f32; 0.5], 0.);
+		assert_eq!(batch_norm.mean(), &mean);
+
+		// the statistics survive serialization, the current batch does not
+		batch_norm.accumulate_batch(&[arr![f32; 100., 100.]], &[arr![f32; 0.5]]);
+		let bytes = bincode::serialize(&batch_norm).unwrap();
+		let mut loaded: BatchNorm<U2, U1, OutputLayer<Sigmoid, U1, U2>> =
+			bincode::deserialize(&bytes).unwrap();
+		assert_eq!(loaded.mean(), batch_norm.mean());
+		assert_eq!(loaded.variance(), batch_norm.variance());
+		let input = arr![f32; 4., -1.];
+		assert_eq!(loaded.calculate(&input), batch_norm.calculate(&input));
+		loaded.apply_batch(1, 0.);
+		assert_eq!(loaded.mean(), batch_norm.mean());
+	}
+
+	#[test]
+	fn trains_deep_sigmoid_stacks() {
+		let lesson = [
+			(arr![f32; -1.], arr![f32; 0.9]),
+			(arr![f32; 0.], arr![f32; 0.1]),
+			(arr![f32; 1.], arr![f32; 0.9]),
+		];
+		fn train<Net: Network<U1, U1>>(
+			network: &mut Net,
+			lesson: &[(GenericArray<f32, U1>, GenericArray<f32, U1>)],
+		) -> f32 {
+			(0..1000).fold(0., |_, _| {
+				network.teach_batches::<3, _>(lesson.iter().cloned(), 0.1)
+			})
+		}
+
+		let mut plain = network![
+			in U1 => Dense<Sigmoid, U4> => Dense<Sigmoid, U4> => Dense<Sigmoid, U4>
+				=> Dense<Sigmoid, U4> => Dense<Sigmoid, U4> => Dense<Sigmoid, U4> => Dense<Sigmoid, U1>
+		];
+		let mut batch_norm = network![
+			in U1 => Dense<Sigmoid, U4> => BatchNorm => Dense<Sigmoid, U4> => BatchNorm
+				=> Dense<Sigmoid, U4> => BatchNorm => Dense<Sigmoid, U4> => BatchNorm
+				=> Dense<Sigmoid, U4> => BatchNorm => Dense<Sigmoid, U4> => BatchNorm
+				=> Dense<Sigmoid, U1>
+		];
+		let mut layer_norm = network![
+			in U1 => Dense<Sigmoid, U4> => LayerNorm => Dense<Sigmoid, U4> => LayerNorm
+				=> Dense<Sigmoid, U4> => LayerNorm => Dense<Sigmoid, U4> => LayerNorm
+				=> Dense<Sigmoid, U4> => LayerNorm => Dense<Sigmoid, U4> => LayerNorm
+				=> Dense<Sigmoid, U1>
+		];
+		let plain = train(&mut plain, &lesson);
+		assert!(plain > 0.1, "{}", plain);
+		let loss = train(&mut batch_norm, &lesson);
+		assert!(loss < 0.01, "{}", loss);
+		let loss = train(&mut layer_norm, &lesson);
+		assert!(loss < 0.01, "{}", loss);
+
+		// single examples are normalized with the running statistics the batches left behind
+		let loss = lesson
+			.iter()
+			.map(|(input, output)| batch_norm.evaluate(input, output))
+			.sum::<f32>()
+			/ 3.;
+		assert!(loss < 0.01, "{}", loss);
+	}
+}
@@ -0,0 +1,232 @@
+//! Optimizers decide how the weights of a [layer](crate::layers) are moved once the direction to
+//! move them in is known.
+//!
+//! Every Optimizer keeps its state in arrays of the same shape as the weights and biases they
+//! belong to, inside of the layer. That way training stays on the stack and the state gets
+//! serialized along with the network, so training can be resumed later on.
+//!
+//! Just like [activations](crate::activation) the Enums in this module are empty and therefore
+//! not initializable. They are only used as type parameters.
+use crate::layers::{AL, NL};
+use core::fmt::Debug;
+use generic_array::GenericArray;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// A set of trainable parameters, like a layers weights or biases.
+pub trait Params: Debug + Clone + Default + Serialize + DeserializeOwned {
+	fn values(&self) -> impl Iterator<Item = &f32>;
+	fn values_mut(&mut self) -> impl Iterator<Item = &mut f32>;
+}
+
+impl<N: AL> Params for GenericArray<f32, N> {
+	fn values(&self) -> impl Iterator<Item = &f32> { self.iter() }
+	fn values_mut(&mut self) -> impl Iterator<Item = &mut f32> { self.iter_mut() }
+}
+
+impl<Input: AL, Neurons: NL<Input>> Params for GenericArray<GenericArray<f32, Input>, Neurons> {
+	fn values(&self) -> impl Iterator<Item = &f32> { self.iter().flat_map(|n| n.iter()) }
+	fn values_mut(&mut self) -> impl Iterator<Item = &mut f32> {
+		self.iter_mut().flat_map(|n| n.iter_mut())
+	}
+}
+
+pub trait Optimizer: Debug + Serialize + DeserializeOwned + Clone {
+	/// what this optimizer needs to remember about a set of parameters between updates
+	type State<P: Params>: Debug + Clone + Default + Serialize + DeserializeOwned;
+
+	/// moves the params by the deltas, which point in the direction the params should move.
+	/// speed is the learning rate and modifies how strongly the params are adjusted
+	fn update<P: Params>(state: &mut Self::State<P>, params: &mut P, deltas: &P, speed: f32);
+}
+
+/// Plain stochastic gradient descent.
+///
+/// params += speed * deltas
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Sgd {}
+
+impl Optimizer for Sgd {
+	type State<P: Params> = ();
+
+	fn update<P: Params>(_state: &mut (), params: &mut P, deltas: &P, speed: f32) {
+		for (param, delta) in params.values_mut().zip(deltas.values()) {
+			*param += speed * delta;
+		}
+	}
+}
+
+/// Gradient descent with a momentum of 0.9.
+///
+/// Keeps moving in the direction it was moving before, which helps getting through flat regions
+/// and smooths out noisy deltas.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Momentum {}
+
+impl Momentum {
+	const MOMENTUM: f32 = 0.9;
+}
+
+impl Optimizer for Momentum {
+	/// the velocity
+	type State<P: Params> = P;
+
+	fn update<P: Params>(velocity: &mut P, params: &mut P, deltas: &P, speed: f32) {
+		for ((param, delta), velocity) in params
+			.values_mut()
+			.zip(deltas.values())
+			.zip(velocity.values_mut())
+		{
+			*velocity = Self::MOMENTUM * *velocity + delta;
+			*param += speed * *velocity;
+		}
+	}
+}
+
+/// Nesterov accelerated gradient with a momentum of 0.9.
+///
+/// Like [Momentum], but corrects the step by looking at where the momentum is about to take the
+/// params.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Nesterov {}
+
+impl Optimizer for Nesterov {
+	/// the velocity
+	type State<P: Params> = P;
+
+	fn update<P: Params>(velocity: &mut P, params: &mut P, deltas: &P, speed: f32) {
+		for ((param, delta), velocity) in params
+			.values_mut()
+			.zip(deltas.values())
+			.zip(velocity.values_mut())
+		{
+			*velocity = Momentum::MOMENTUM * *velocity + delta;
+			*param += speed * (Momentum::MOMENTUM * *velocity + delta);
+		}
+	}
+}
+
+/// RMSProp with a decay of 0.9.
+///
+/// Divides each delta by a running average of its magnitude, so all params move at a similar
+/// pace.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum RmsProp {}
+
+impl RmsProp {
+	const DECAY: f32 = 0.9;
+	const EPSILON: f32 = 1e-8;
+}
+
+impl Optimizer for RmsProp {
+	/// the running average of the squared deltas
+	type State<P: Params> = P;
+
+	fn update<P: Params>(squares: &mut P, params: &mut P, deltas: &P, speed: f32) {
+		for ((param, delta), square) in params
+			.values_mut()
+			.zip(deltas.values())
+			.zip(squares.values_mut())
+		{
+			*square = Self::DECAY * *square + (1. - Self::DECAY) * delta * delta;
+			*param += speed * delta / (square.sqrt() + Self::EPSILON);
+		}
+	}
+}
+
+/// Adam with the usual betas of 0.9 and 0.999.
+///
+/// Combines [Momentum] and [RmsProp], its steps are roughly speed sized, so it works best with
+/// small speeds like 0.001.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Adam {}
+
+impl Adam {
+	const BETA1: f32 = 0.9;
+	const BETA2: f32 = 0.999;
+	const EPSILON: f32 = 1e-8;
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(bound = "P: Params")]
+pub struct AdamState<P: Params> {
+	/// running average of the deltas
+	mean: P,
+	/// running average of the squared deltas
+	variance: P,
+	/// number of updates so far, used to correct the bias towards 0 of the averages
+	steps: i32,
+}
+
+impl Optimizer for Adam {
+	type State<P: Params> = AdamState<P>;
+
+	fn update<P: Params>(state: &mut AdamState<P>, params: &mut P, deltas: &P, speed: f32) {
+		state.steps = state.steps.saturating_add(1);
+		let mean_correction = 1. - Self::BETA1.powi(state.steps);
+		let variance_correction = 1. - Self::BETA2.powi(state.steps);
+		for (((param, delta), mean), variance) in params
+			.values_mut()
+			.zip(deltas.values())
+			.zip(state.mean.values_mut())
+			.zip(state.variance.values_mut())
+		{
+			*mean = Self::BETA1 * *mean + (1. - Self::BETA1) * delta;
+			*variance = Self::BETA2 * *variance + (1. - Self::BETA2) * delta * delta;
+			let mean = *mean / mean_correction;
+			let variance = *variance / variance_correction;
+			*param += speed * mean / (variance.sqrt() + Self::EPSILON);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use generic_array::{arr, typenum::U3};
+
+	/// walks towards the minimum of sum((params - target)^2)
+	fn minimize<O: Optimizer>(speed: f32) {
+		let target = arr![f32; 1., -2., 0.5];
+		let mut params = GenericArray::<f32, U3>::default();
+		let mut state = O::State::<GenericArray<f32, U3>>::default();
+		for _ in 0..1000 {
+			let deltas = params
+				.iter()
+				.zip(&target)
+				.map(|(param, target)| 2. * (target - param))
+				.collect();
+			O::update(&mut state, &mut params, &deltas, speed);
+		}
+		for (param, target) in params.iter().zip(&target) {
+			assert!((param - target).abs() < 1e-2, "{:?}", params);
+		}
+	}
+
+	#[test]
+	fn converges() {
+		minimize::<Sgd>(0.1);
+		minimize::<Momentum>(0.01);
+		minimize::<Nesterov>(0.01);
+		minimize::<RmsProp>(0.005);
+		minimize::<Adam>(0.05);
+	}
+
+	#[test]
+	fn state_is_serialized() {
+		use crate::{
+			activation::Sigmoid,
+			layers::{Network, OutputLayer},
+			loss::Mse,
+		};
+		use generic_array::typenum::{U1, U2};
+
+		let mut layer = OutputLayer::<Sigmoid, U1, U2, Mse, Adam>::new();
+		layer.backprop(&arr![f32; 1., 0.], &arr![f32; 1.], 0.01);
+
+		let bytes = bincode::serialize(&layer).unwrap();
+		let restored: OutputLayer<Sigmoid, U1, U2, Mse, Adam> =
+			bincode::deserialize(&bytes).unwrap();
+		assert_eq!(restored.bias_state.steps, 1);
+		assert_eq!(format!("{:?}", restored), format!("{:?}", layer));
+	}
+}
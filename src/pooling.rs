@@ -0,0 +1,629 @@
+//! Layers shrinking each plane of a 3d input, like the output of a
+//! [ConvolutionalLayer](crate::convolution::ConvolutionalLayer), by combining every window of it
+//! into a single value.
+//!
+//! They use the same layout as a convolution with one filter per plane, so they fit between a
+//! convolution and the layers below it. The windows move SH rows and SW columns at a time, just
+//! like the filters of a convolution. Pooling has no weights, so nothing is learned.
+//!
+//! for generic layer documentation see [layers](crate::layers)
+use core::ops::{Add, Div, Mul, Sub};
+use generic_array::{
+	typenum::{bit::B1, Add1, Diff, Prod, Quot, Unsigned},
+	ArrayLength, GenericArray,
+};
+
+use crate::layers::{map_batch, sealed, Differentiable, AL};
+
+/// the number of outputs of a pooling layer
+pub type PoolOutputSize<W, H, D, PW, PH, SH, SW> =
+	Prod<Prod<PoolHSize<H, PH, SH>, PoolWSize<W, PW, SW>>, D>;
+/// the height of each plane of the output
+pub type PoolHSize<H, PH, SH> = Add1<Quot<Diff<H, PH>, SH>>;
+/// the width of each plane of the output
+pub type PoolWSize<W, PW, SW> = Add1<Quot<Diff<W, PW>, SW>>;
+
+/// calls `f(output, input)` for every input in the window of every output
+#[inline(always)]
+fn for_each_window<W, H, D, PW, PH, SH, SW, F>(mut f: F)
+where
+	W: Unsigned,
+	H: Unsigned,
+	D: Unsigned,
+	PW: Unsigned,
+	PH: Unsigned,
+	SH: Unsigned,
+	SW: Unsigned,
+	F: FnMut(usize, usize),
+{
+	let width = W::to_usize();
+	let height = H::to_usize();
+	let window_width = PW::to_usize();
+	let window_height = PH::to_usize();
+	let stride_height = SH::to_usize();
+	let stride_width = SW::to_usize();
+	let output_height = (height - window_height) / stride_height + 1;
+	let output_width = (width - window_width) / stride_width + 1;
+
+	for d in 0..D::to_usize() {
+		for out_y in 0..output_height {
+			for out_x in 0..output_width {
+				let output = (d * output_height + out_y) * output_width + out_x;
+				for window_y in 0..window_height {
+					let y = out_y * stride_height + window_y;
+					for window_x in 0..window_width {
+						let x = out_x * stride_width + window_x;
+						f(output, (d * height + y) * width + x);
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Passes on the largest value of each PW * PH window of each of the D planes of its input.
+///
+/// The error of each output goes back to the input that was the largest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "W: AL, H: AL, D: AL, PW: AL, PH: AL, SH: AL, SW: AL, FinalOut: AL")]
+pub struct MaxPool2d<
+	// width of the input
+	W: AL,
+	// height of the input
+	H: AL,
+	// depth of the input
+	D: AL,
+	// width of the window
+	PW: AL,
+	// height of the window
+	PH: AL,
+	// rows the window moves at a time
+	SH: AL,
+	// columns the window moves at a time
+	SW: AL,
+	FinalOut: AL,
+	Next: Differentiable<PoolOutputSize<W, H, D, PW, PH, SH, SW>, FinalOut>,
+> where
+	H: Sub<PH>,
+	Diff<H, PH>: Div<SH>,
+	Quot<Diff<H, PH>, SH>: Add<B1>,
+
+	W: Sub<PW>,
+	Diff<W, PW>: Div<SW>,
+	Quot<Diff<W, PW>, SW>: Add<B1>,
+
+	PoolHSize<H, PH, SH>: Mul<PoolWSize<W, PW, SW>>,
+	Prod<PoolHSize<H, PH, SH>, PoolWSize<W, PW, SW>>: Mul<D>,
+
+	PoolOutputSize<W, H, D, PW, PH, SH, SW>: AL,
+{
+	phantom: core::marker::PhantomData<(W, H, D, PW, PH, SH, SW, FinalOut)>,
+	next: Next,
+}
+
+impl<
+		W: AL,
+		H: AL,
+		D: AL,
+		PW: AL,
+		PH: AL,
+		SH: AL,
+		SW: AL,
+		FinalOut: AL,
+		Next: Differentiable<PoolOutputSize<W, H, D, PW, PH, SH, SW>, FinalOut>,
+	> MaxPool2d<W, H, D, PW, PH, SH, SW, FinalOut, Next>
+where
+	H: Mul<W>,
+	Prod<H, W>: Mul<D>,
+	Prod<Prod<H, W>, D>: AL,
+
+	H: Sub<PH>,
+	Diff<H, PH>: Div<SH>,
+	Quot<Diff<H, PH>, SH>: Add<B1>,
+
+	W: Sub<PW>,
+	Diff<W, PW>: Div<SW>,
+	Quot<Diff<W, PW>, SW>: Add<B1>,
+
+	PoolHSize<H, PH, SH>: Mul<PoolWSize<W, PW, SW>>,
+	Prod<PoolHSize<H, PH, SH>, PoolWSize<W, PW, SW>>: Mul<D>,
+
+	PoolOutputSize<W, H, D, PW, PH, SH, SW>: AL + ArrayLength<usize>,
+{
+	/// Pushes this layer on top of an existing layer.
+	pub fn push(next: Next) -> Self {
+		Self {
+			phantom: core::marker::PhantomData,
+			next,
+		}
+	}
+
+	/// the largest value of every window and the index of the input it came from
+	fn pool(
+		inputs: &GenericArray<f32, Prod<Prod<H, W>, D>>,
+	) -> (
+		GenericArray<f32, PoolOutputSize<W, H, D, PW, PH, SH, SW>>,
+		GenericArray<usize, PoolOutputSize<W, H, D, PW, PH, SH, SW>>,
+	) {
+		let mut outputs = GenericArray::<f32, PoolOutputSize<W, H, D, PW, PH, SH, SW>>::default();
+		let mut argmax = GenericArray::<usize, PoolOutputSize<W, H, D, PW, PH, SH, SW>>::default();
+		argmax.iter_mut().for_each(|a| *a = usize::MAX);
+		for_each_window::<W, H, D, PW, PH, SH, SW, _>(|output, input| {
+			// each window starts out with its first input, so one without anything larger, like
+			// all NaN or all -inf, still routes its error to an input inside of it
+			if argmax[output] == usize::MAX || inputs[input] > outputs[output] {
+				outputs[output] = inputs[input];
+				argmax[output] = input;
+			}
+		});
+		(outputs, argmax)
+	}
+}
+
+impl<
+		W: AL,
+		H: AL,
+		D: AL,
+		PW: AL,
+		PH: AL,
+		SH: AL,
+		SW: AL,
+		FinalOut: AL,
+		Next: Differentiable<PoolOutputSize<W, H, D, PW, PH, SH, SW>, FinalOut>,
+	> sealed::Sealed for MaxPool2d<W, H, D, PW, PH, SH, SW, FinalOut, Next>
+where
+	H: Sub<PH>,
+	Diff<H, PH>: Div<SH>,
+	Quot<Diff<H, PH>, SH>: Add<B1>,
+
+	W: Sub<PW>,
+	Diff<W, PW>: Div<SW>,
+	Quot<Diff<W, PW>, SW>: Add<B1>,
+
+	PoolHSize<H, PH, SH>: Mul<PoolWSize<W, PW, SW>>,
+	Prod<PoolHSize<H, PH, SH>, PoolWSize<W, PW, SW>>: Mul<D>,
+
+	PoolOutputSize<W, H, D, PW, PH, SH, SW>: AL,
+{
+}
+
+impl<
+		W: AL,
+		H: AL,
+		D: AL,
+		PW: AL,
+		PH: AL,
+		SH: AL,
+		SW: AL,
+		FinalOut: AL,
+		Next: Differentiable<PoolOutputSize<W, H, D, PW, PH, SH, SW>, FinalOut>,
+	> Differentiable<Prod<Prod<H, W>, D>, FinalOut>
+	for MaxPool2d<W, H, D, PW, PH, SH, SW, FinalOut, Next>
+where
+	H: Mul<W>,
+	Prod<H, W>: Mul<D>,
+	Prod<Prod<H, W>, D>: AL,
+
+	H: Sub<PH>,
+	Diff<H, PH>: Div<SH>,
+	Quot<Diff<H, PH>, SH>: Add<B1>,
+
+	W: Sub<PW>,
+	Diff<W, PW>: Div<SW>,
+	Quot<Diff<W, PW>, SW>: Add<B1>,
+
+	PoolHSize<H, PH, SH>: Mul<PoolWSize<W, PW, SW>>,
+	Prod<PoolHSize<H, PH, SH>, PoolWSize<W, PW, SW>>: Mul<D>,
+
+	PoolOutputSize<W, H, D, PW, PH, SH, SW>: AL + ArrayLength<usize>,
+{
+	fn _calculate(
+		&self,
+		inputs: &GenericArray<f32, Prod<Prod<H, W>, D>>,
+	) -> GenericArray<f32, FinalOut> {
+		self.next._calculate(&Self::pool(inputs).0)
+	}
+
+	fn _loss(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> f32 {
+		Next::_loss(output, correct_output)
+	}
+
+	fn _error(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> (GenericArray<f32, FinalOut>, f32) {
+		Next::_error(output, correct_output)
+	}
+
+	fn _accumulate_with<F>(
+		&mut self,
+		inputs: &GenericArray<f32, Prod<Prod<H, W>, D>>,
+		objective: F,
+	) -> (GenericArray<f32, Prod<Prod<H, W>, D>>, f32)
+	where
+		F: FnOnce(&GenericArray<f32, FinalOut>) -> (GenericArray<f32, FinalOut>, f32),
+	{
+		// the argmax stays on the stack until the error comes back from the lower layers
+		let (output, argmax) = Self::pool(inputs);
+		let (error, loss) = self.next._accumulate_with(&output, objective);
+		let mut pre_error = GenericArray::<f32, Prod<Prod<H, W>, D>>::default();
+		for (error, &input) in error.iter().zip(&argmax) {
+			pre_error[input] += error;
+		}
+		(pre_error, loss)
+	}
+
+	fn _accumulate_batch<const B: usize, F>(
+		&mut self,
+		inputs: &[GenericArray<f32, Prod<Prod<H, W>, D>>; B],
+		objective: F,
+	) -> ([GenericArray<f32, Prod<Prod<H, W>, D>>; B], f32)
+	where
+		F: FnOnce(&[GenericArray<f32, FinalOut>; B]) -> ([GenericArray<f32, FinalOut>; B], f32),
+	{
+		let pooled = inputs.each_ref().map(Self::pool);
+		let outputs = pooled.each_ref().map(|(output, _)| output.clone());
+		let (errors, loss) = self.next._accumulate_batch(&outputs, objective);
+		let pre_errors = map_batch(errors, |i, error| {
+			let mut pre_error = GenericArray::<f32, Prod<Prod<H, W>, D>>::default();
+			for (error, &input) in error.iter().zip(&pooled[i].1) {
+				pre_error[input] += error;
+			}
+			pre_error
+		});
+		(pre_errors, loss)
+	}
+
+	fn _apply_deltas(&mut self, batch_size: usize, speed: f32) {
+		self.next._apply_deltas(batch_size, speed);
+	}
+
+	fn _params_mut<F: FnMut(&mut f32, &mut f32)>(&mut self, f: &mut F) { self.next._params_mut(f); }
+
+	fn _penalty(&self) -> f32 { self.next._penalty() }
+}
+
+/// Passes on the average of each PW * PH window of each of the D planes of its input.
+///
+/// The error of each output is split evenly between the inputs of its window.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "W: AL, H: AL, D: AL, PW: AL, PH: AL, SH: AL, SW: AL, FinalOut: AL")]
+pub struct AvgPool2d<
+	// width of the input
+	W: AL,
+	// height of the input
+	H: AL,
+	// depth of the input
+	D: AL,
+	// width of the window
+	PW: AL,
+	// height of the window
+	PH: AL,
+	// rows the window moves at a time
+	SH: AL,
+	// columns the window moves at a time
+	SW: AL,
+	FinalOut: AL,
+	Next: Differentiable<PoolOutputSize<W, H, D, PW, PH, SH, SW>, FinalOut>,
+> where
+	H: Sub<PH>,
+	Diff<H, PH>: Div<SH>,
+	Quot<Diff<H, PH>, SH>: Add<B1>,
+
+	W: Sub<PW>,
+	Diff<W, PW>: Div<SW>,
+	Quot<Diff<W, PW>, SW>: Add<B1>,
+
+	PoolHSize<H, PH, SH>: Mul<PoolWSize<W, PW, SW>>,
+	Prod<PoolHSize<H, PH, SH>, PoolWSize<W, PW, SW>>: Mul<D>,
+
+	PoolOutputSize<W, H, D, PW, PH, SH, SW>: AL,
+{
+	phantom: core::marker::PhantomData<(W, H, D, PW, PH, SH, SW, FinalOut)>,
+	next: Next,
+}
+
+impl<
+		W: AL,
+		H: AL,
+		D: AL,
+		PW: AL,
+		PH: AL,
+		SH: AL,
+		SW: AL,
+		FinalOut: AL,
+		Next: Differentiable<PoolOutputSize<W, H, D, PW, PH, SH, SW>, FinalOut>,
+	> AvgPool2d<W, H, D, PW, PH, SH, SW, FinalOut, Next>
+where
+	H: Mul<W>,
+	Prod<H, W>: Mul<D>,
+	Prod<Prod<H, W>, D>: AL,
+
+	H: Sub<PH>,
+	Diff<H, PH>: Div<SH>,
+	Quot<Diff<H, PH>, SH>: Add<B1>,
+
+	W: Sub<PW>,
+	Diff<W, PW>: Div<SW>,
+	Quot<Diff<W, PW>, SW>: Add<B1>,
+
+	PoolHSize<H, PH, SH>: Mul<PoolWSize<W, PW, SW>>,
+	Prod<PoolHSize<H, PH, SH>, PoolWSize<W, PW, SW>>: Mul<D>,
+
+	PoolOutputSize<W, H, D, PW, PH, SH, SW>: AL,
+{
+	/// Pushes this layer on top of an existing layer.
+	pub fn push(next: Next) -> Self {
+		Self {
+			phantom: core::marker::PhantomData,
+			next,
+		}
+	}
+
+	/// the weight of each input in the average of its window
+	fn share() -> f32 { 1. / (PW::to_usize() * PH::to_usize()) as f32 }
+
+	fn pool(
+		inputs: &GenericArray<f32, Prod<Prod<H, W>, D>>,
+	) -> GenericArray<f32, PoolOutputSize<W, H, D, PW, PH, SH, SW>> {
+		let mut outputs = GenericArray::<f32, PoolOutputSize<W, H, D, PW, PH, SH, SW>>::default();
+		for_each_window::<W, H, D, PW, PH, SH, SW, _>(|output, input| {
+			outputs[output] += inputs[input] * Self::share();
+		});
+		outputs
+	}
+}
+
+impl<
+		W: AL,
+		H: AL,
+		D: AL,
+		PW: AL,
+		PH: AL,
+		SH: AL,
+		SW: AL,
+		FinalOut: AL,
+		Next: Differentiable<PoolOutputSize<W, H, D, PW, PH, SH, SW>, FinalOut>,
+	> sealed::Sealed for AvgPool2d<W, H, D, PW, PH, SH, SW, FinalOut, Next>
+where
+	H: Sub<PH>,
+	Diff<H, PH>: Div<SH>,
+	Quot<Diff<H, PH>, SH>: Add<B1>,
+
+	W: Sub<PW>,
+	Diff<W, PW>: Div<SW>,
+	Quot<Diff<W, PW>, SW>: Add<B1>,
+
+	PoolHSize<H, PH, SH>: Mul<PoolWSize<W, PW, SW>>,
+	Prod<PoolHSize<H, PH, SH>, PoolWSize<W, PW, SW>>: Mul<D>,
+
+	PoolOutputSize<W, H, D, PW, PH, SH, SW>: AL,
+{
+}
+
+impl<
+		W: AL,
+		H: AL,
+		D: AL,
+		PW: AL,
+		PH: AL,
+		SH: AL,
+		SW: AL,
+		FinalOut: AL,
+		Next: Differentiable<PoolOutputSize<W, H, D, PW, PH, SH, SW>, FinalOut>,
+	> Differentiable<Prod<Prod<H, W>, D>, FinalOut>
+	for AvgPool2d<W, H, D, PW, PH, SH, SW, FinalOut, Next>
+where
+	H: Mul<W>,
+	Prod<H, W>: Mul<D>,
+	Prod<Prod<H, W>, D>: AL,
+
+	H: Sub<PH>,
+	Diff<H, PH>: Div<SH>,
+	Quot<Diff<H, PH>, SH>: Add<B1>,
+
+	W: Sub<PW>,
+	Diff<W, PW>: Div<SW>,
+	Quot<Diff<W, PW>, SW>: Add<B1>,
+
+	PoolHSize<H, PH, SH>: Mul<PoolWSize<W, PW, SW>>,
+	Prod<PoolHSize<H, PH, SH>, PoolWSize<W, PW, SW>>: Mul<D>,
+
+	PoolOutputSize<W, H, D, PW, PH, SH, SW>: AL,
+{
+	fn _calculate(
+		&self,
+		inputs: &GenericArray<f32, Prod<Prod<H, W>, D>>,
+	) -> GenericArray<f32, FinalOut> {
+		self.next._calculate(&Self::pool(inputs))
+	}
+
+	fn _loss(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> f32 {
+		Next::_loss(output, correct_output)
+	}
+
+	fn _error(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> (GenericArray<f32, FinalOut>, f32) {
+		Next::_error(output, correct_output)
+	}
+
+	fn _accumulate_with<F>(
+		&mut self,
+		inputs: &GenericArray<f32, Prod<Prod<H, W>, D>>,
+		objective: F,
+	) -> (GenericArray<f32, Prod<Prod<H, W>, D>>, f32)
+	where
+		F: FnOnce(&GenericArray<f32, FinalOut>) -> (GenericArray<f32, FinalOut>, f32),
+	{
+		let (error, loss) = self.next._accumulate_with(&Self::pool(inputs), objective);
+		let mut pre_error = GenericArray::<f32, Prod<Prod<H, W>, D>>::default();
+		for_each_window::<W, H, D, PW, PH, SH, SW, _>(|output, input| {
+			pre_error[input] += error[output] * Self::share();
+		});
+		(pre_error, loss)
+	}
+
+	fn _accumulate_batch<const B: usize, F>(
+		&mut self,
+		inputs: &[GenericArray<f32, Prod<Prod<H, W>, D>>; B],
+		objective: F,
+	) -> ([GenericArray<f32, Prod<Prod<H, W>, D>>; B], f32)
+	where
+		F: FnOnce(&[GenericArray<f32, FinalOut>; B]) -> ([GenericArray<f32, FinalOut>; B], f32),
+	{
+		let outputs = inputs.each_ref().map(Self::pool);
+		let (errors, loss) = self.next._accumulate_batch(&outputs, objective);
+		let pre_errors = errors.map(|error| {
+			let mut pre_error = GenericArray::<f32, Prod<Prod<H, W>, D>>::default();
+			for_each_window::<W, H, D, PW, PH, SH, SW, _>(|output, input| {
+				pre_error[input] += error[output] * Self::share();
+			});
+			pre_error
+		});
+		(pre_errors, loss)
+	}
+
+	fn _apply_deltas(&mut self, batch_size: usize, speed: f32) {
+		self.next._apply_deltas(batch_size, speed);
+	}
+
+	fn _params_mut<F: FnMut(&mut f32, &mut f32)>(&mut self, f: &mut F) { self.next._params_mut(f); }
+
+	fn _penalty(&self) -> f32 { self.next._penalty() }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		activation::{ReLu, Sigmoid},
+		convolution::{ConvolutionalLayer, Valid},
+		gradcheck::check,
+		layers::{InnerLayer, Network, OutputLayer},
+		testing::{assert_batch_matches, sample},
+	};
+	use generic_array::{
+		arr,
+		typenum::{U1, U2, U24, U3, U36, U4, U6, U8},
+	};
+
+	// a 4x3 input with 2 planes, pooled in 2x2 windows
+	type Output = OutputLayer<Sigmoid, U2, U4>;
+	type Max = MaxPool2d<U4, U3, U2, U2, U2, U2, U2, U2, Output>;
+	type Avg = AvgPool2d<U4, U3, U2, U2, U2, U2, U2, U2, Output>;
+
+	#[rustfmt::skip]
+	fn input() -> GenericArray<f32, U24> {
+		arr![f32;
+			0.1, 0.5, -0.3, 0.2,
+			0.4, -0.2, 0.9, 0.0,
+			0.7, 0.8, 0.6, 0.3,
+
+			-0.5, -0.1, 0.3, -0.4,
+			-0.7, -0.6, 0.35, 0.45,
+			0.05, 0.15, 0.25, -0.9
+		]
+	}
+
+	#[test]
+	fn pools_windows() {
+		assert_eq!(PoolOutputSize::<U4, U3, U2, U2, U2, U2, U2>::to_usize(), 4);
+		assert_eq!(Max::pool(&input()).0, arr![f32; 0.5, 0.9, -0.1, 0.45]);
+		assert_eq!(Max::pool(&input()).1, arr![usize; 1, 6, 13, 19]);
+
+		let average = Avg::pool(&input());
+		for (average, expected) in average.iter().zip(&[0.2, 0.2, -0.475, 0.175]) {
+			assert!((average - expected).abs() < 1e-6, "{:?}", average);
+		}
+	}
+
+	#[test]
+	fn strides_rows_and_columns_separately() {
+		// overlapping windows one row apart, but two columns apart
+		type Strided = MaxPool2d<U4, U3, U2, U2, U2, U1, U2, U2, OutputLayer<Sigmoid, U2, U8>>;
+		assert_eq!(PoolOutputSize::<U4, U3, U2, U2, U2, U1, U2>::to_usize(), 8);
+		let (output, argmax) = Strided::pool(&input());
+		assert_eq!(
+			output,
+			arr![f32; 0.5, 0.9, 0.8, 0.9, -0.1, 0.45, 0.15, 0.45]
+		);
+		assert_eq!(argmax, arr![usize; 1, 6, 9, 6, 13, 19, 21, 19]);
+	}
+
+	#[test]
+	fn picks_an_input_of_windows_without_a_maximum() {
+		let mut input = input();
+		for &i in &[2, 3, 6, 7] {
+			input[i] = f32::NEG_INFINITY;
+		}
+		for &i in &[14, 15, 18, 19] {
+			input[i] = f32::NAN;
+		}
+		let (output, argmax) = Max::pool(&input);
+		assert_eq!(output[1], f32::NEG_INFINITY);
+		assert!(output[3].is_nan());
+		assert_eq!(argmax, arr![usize; 1, 2, 13, 14]);
+	}
+
+	#[test]
+	fn routes_errors_to_the_maximum() {
+		let mut max = Max::push(Output::new());
+		let (error, _) = max.accumulate(&input(), &arr![f32; 1., 0.]);
+		for (i, error) in error.iter().enumerate() {
+			assert_eq!(*error != 0., [1, 6, 13, 19].contains(&i), "{}", i);
+		}
+	}
+
+	#[test]
+	fn gradients() {
+		let result = check(
+			&Max::push(Output::new()),
+			&input(),
+			&arr![f32; 0., 1.],
+			1e-3,
+			1e-2,
+		);
+		assert!(result.is_ok(), "{:?}", result);
+		let result = check(
+			&Avg::push(Output::new()),
+			&input(),
+			&arr![f32; 0., 1.],
+			1e-2,
+			1e-2,
+		);
+		assert!(result.is_ok(), "{:?}", result);
+	}
+
+	#[test]
+	fn batches() {
+		let inputs = [input(), sample(1), sample(2)];
+		let outputs = [arr![f32; 0., 1.], arr![f32; 1., 0.], arr![f32; 1., 1.]];
+		assert_batch_matches(&Max::push(Output::new()), &inputs, &outputs);
+		assert_batch_matches(&Avg::push(Output::new()), &inputs, &outputs);
+	}
+
+	#[test]
+	fn sits_between_convolution_and_dense_layers() {
+		// a 6x6 image, 2 3x3 filters, pooled down to 2 2x2 planes
+		type Head = InnerLayer<Sigmoid, U4, U8, U1, OutputLayer<Sigmoid, U1, U4>>;
+		type Pool = MaxPool2d<U4, U4, U2, U2, U2, U2, U2, U1, Head>;
+		type Conv = ConvolutionalLayer<ReLu, U6, U6, U1, U3, U3, U2, U1, U1, Valid, U1, U1, Pool>;
+		let mut network = Conv::push(Pool::push(Head::push(OutputLayer::new())));
+
+		let mut image = GenericArray::<f32, U36>::default();
+		image[14] = 1.;
+		let before = network.evaluate(&image, &arr![f32; 1.]);
+		for _ in 0..50 {
+			network.backprop(&image, &arr![f32; 1.], 0.5);
+		}
+		assert!(network.evaluate(&image, &arr![f32; 1.]) < before);
+	}
+}
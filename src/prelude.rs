@@ -1,6 +1,14 @@
-pub use crate::activation::{ReLu, Sigmoid, SoftMax};
-//pub use crate::convolution::ConvolutionalLayer;
 pub use crate::{
-	layers::{InnerLayer, Layer, OutputLayer},
+	activation::{ReLu, Sigmoid, SoftMax},
+	conv1d::Conv1d,
+	convolution::ConvolutionalLayer,
+	dense::{to_array, to_generic, Dense},
+	depthwise::{DepthwiseConv2d, PointwiseConv2d},
+	dropout::Dropout,
+	layers::{InnerLayer, Network, OutputLayer},
+	network,
+	normalization::{BatchNorm, LayerNorm},
+	pooling::{AvgPool2d, MaxPool2d},
 	softmax::SoftMax as SoftMaxLayer,
+	upsampling::{ConvTranspose2d, Upsample2d},
 };
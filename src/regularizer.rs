@@ -0,0 +1,242 @@
+//! Regularization keeps the weights of a layer small, which helps against overfitting.
+//!
+//! Every layer has its own [Regularizer], set with
+//! [OutputLayer::with_regularizer](crate::layers::OutputLayer::with_regularizer) and friends.
+//! It only applies to the weights, biases are left alone.
+//!
+//! ```
+//! use dumbnet::{
+//!     activation::Sigmoid,
+//!     layers::{InnerLayer, OutputLayer},
+//!     regularizer::Regularizer,
+//! };
+//! use generic_array::typenum::{U1, U2, U4};
+//!
+//! let output = OutputLayer::<Sigmoid, U1, U4>::new()
+//!     .with_regularizer(Regularizer::default().with_l2(1e-3));
+//! let network = InnerLayer::<Sigmoid, U4, U2, _, _>::push(output)
+//!     .with_regularizer(Regularizer::default().with_max_norm(3.));
+//! ```
+use crate::{
+	initializer::Weights,
+	layers::apply_deltas,
+	optimizer::{Optimizer, Params},
+};
+
+/// How strongly the weights of a layer get pulled towards zero.
+///
+/// The penalties are added to the deltas of each batch before the [Optimizer] sees them, so
+/// optimizers that rescale the deltas like [Adam](crate::optimizer::Adam) rescale the penalties
+/// as well. Decoupled decay and the max-norm constraint are applied to the weights directly after
+/// the optimizer has moved them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Regularizer {
+	l1: f32,
+	l2: f32,
+	decay: f32,
+	max_norm: Option<f32>,
+	reported: bool,
+}
+
+impl Regularizer {
+	/// adds strength * |w| to the loss, pulling every weight towards zero by the same amount
+	pub fn with_l1(self, strength: f32) -> Self {
+		Self {
+			l1: strength,
+			..self
+		}
+	}
+
+	/// adds strength / 2 * w² to the loss, pulling every weight towards zero in proportion to its
+	/// size
+	pub fn with_l2(self, strength: f32) -> Self {
+		Self {
+			l2: strength,
+			..self
+		}
+	}
+
+	/// shrinks every weight by speed * decay * w after each update, independent of the optimizer
+	/// like in AdamW
+	pub fn with_decoupled_decay(self, decay: f32) -> Self { Self { decay, ..self } }
+
+	/// rescales the weights of each neuron after each update so their euclidean norm is at most
+	/// max_norm
+	pub fn with_max_norm(self, max_norm: f32) -> Self {
+		Self {
+			max_norm: Some(max_norm),
+			..self
+		}
+	}
+
+	/// adds the l1 and l2 penalty to the loss [teach](crate::layers::Network::teach) reports
+	pub fn with_reported_penalty(self) -> Self {
+		Self {
+			reported: true,
+			..self
+		}
+	}
+
+	/// the l1 and l2 penalty of the weights
+	pub fn penalty<P: Params>(&self, weights: &P) -> f32 {
+		weights
+			.values()
+			.map(|w| self.l1 * w.abs() + self.l2 / 2. * w * w)
+			.sum()
+	}
+
+	/// the penalty of the weights if it should be reported, otherwise 0
+	pub(crate) fn reported_penalty<P: Params>(&self, weights: &P) -> f32 {
+		if self.reported {
+			self.penalty(weights)
+		} else {
+			0.
+		}
+	}
+
+	/// like [apply_deltas], but regularizes the weights along the way
+	pub(crate) fn apply_deltas<O: Optimizer, P: Params + Weights>(
+		&self,
+		weights: &mut P,
+		state: &mut O::State<P>,
+		deltas: &mut P,
+		batch_size: usize,
+		speed: f32,
+	) {
+		// the deltas are the sum of the batch, so the penalty has to count batch_size times
+		let batch = batch_size as f32;
+		for (weight, delta) in weights.values().zip(deltas.values_mut()) {
+			let sign = if *weight == 0. { 0. } else { weight.signum() };
+			*delta -= batch * (self.l1 * sign + self.l2 * weight);
+		}
+
+		apply_deltas::<O, _>(weights, state, deltas, batch_size, speed);
+
+		if self.decay != 0. {
+			let factor = 1. - speed * self.decay;
+			weights.values_mut().for_each(|w| *w *= factor);
+		}
+		if let Some(max_norm) = self.max_norm {
+			for neuron in 0..weights.neurons() {
+				let norm = (0..weights.inputs())
+					.map(|i| weights.weight(neuron, i).powi(2))
+					.sum::<f32>()
+					.sqrt();
+				if norm > max_norm {
+					let scale = max_norm / norm;
+					for i in 0..weights.inputs() {
+						*weights.weight_mut(neuron, i) *= scale;
+					}
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		activation::Sigmoid,
+		layers::{InnerLayer, Network, OutputLayer},
+		loss::Mse,
+		optimizer::{Adam, Sgd},
+	};
+	use generic_array::{
+		arr,
+		typenum::{U1, U2, U3},
+		GenericArray,
+	};
+
+	type Layer<O> = OutputLayer<Sigmoid, U2, U3, Mse, O>;
+
+	/// applies a batch without any deltas, so only the regularizer moves the weights
+	fn step<O: Optimizer>(layer: &mut Layer<O>) -> Layer<O> {
+		let before = layer.clone();
+		layer.apply_batch(1, 0.1);
+		before
+	}
+
+	#[test]
+	fn pulls_towards_zero() {
+		let mut l1 = Layer::<Sgd>::new().with_regularizer(Regularizer::default().with_l1(0.5));
+		let before = step(&mut l1);
+		for (after, before) in l1
+			.weights
+			.iter()
+			.flatten()
+			.zip(before.weights.iter().flatten())
+		{
+			assert!((after - (before - 0.05 * before.signum())).abs() < 1e-6);
+		}
+
+		let mut l2 = Layer::<Sgd>::new().with_regularizer(Regularizer::default().with_l2(0.5));
+		let before = step(&mut l2);
+		for (after, before) in l2
+			.weights
+			.iter()
+			.flatten()
+			.zip(before.weights.iter().flatten())
+		{
+			assert!((after - before * 0.95).abs() < 1e-6);
+		}
+		// biases are left alone
+		assert_eq!(l2.bias, before.bias);
+	}
+
+	#[test]
+	fn decouples_decay_from_the_optimizer() {
+		// adam turns even a tiny l2 gradient into a step of about speed
+		let mut coupled =
+			Layer::<Adam>::new().with_regularizer(Regularizer::default().with_l2(1e-3));
+		let before = step(&mut coupled);
+		let moved = coupled.weights[0][0] - before.weights[0][0];
+		assert!((moved.abs() - 0.1).abs() < 1e-3, "{}", moved);
+
+		let mut decoupled = Layer::<Adam>::new()
+			.with_regularizer(Regularizer::default().with_decoupled_decay(1e-3));
+		let before = step(&mut decoupled);
+		for (after, before) in decoupled
+			.weights
+			.iter()
+			.flatten()
+			.zip(before.weights.iter().flatten())
+		{
+			assert!((after - before * (1. - 1e-4)).abs() < 1e-7);
+		}
+	}
+
+	#[test]
+	fn limits_the_norm() {
+		let mut layer = Layer::<Sgd>::new().with_regularizer(Regularizer::default().with_max_norm(0.5));
+		layer.weights = arr![GenericArray<f32, U3>; arr![f32; 3., 4., 0.], arr![f32; 0.1, 0.2, 0.]];
+		step(&mut layer);
+		for (weight, expected) in layer.weights[0].iter().zip(&[0.3, 0.4, 0.]) {
+			assert!((weight - expected).abs() < 1e-6);
+		}
+		// neurons within the limit are left alone
+		assert_eq!(layer.weights[1], arr![f32; 0.1, 0.2, 0.]);
+	}
+
+	#[test]
+	fn reports_the_penalty() {
+		let lesson = [(arr![f32; 0.2, 0.4, 0.6], arr![f32; 1., 0.])];
+		let regularizer = Regularizer::default().with_l1(0.1).with_l2(0.2);
+		let mut quiet = Layer::<Sgd>::new().with_regularizer(regularizer);
+		let mut reported = Layer::<Sgd>::new().with_regularizer(regularizer.with_reported_penalty());
+
+		let loss = quiet.teach_epoch(lesson.iter().cloned(), 1, 0.);
+		let with_penalty = reported.teach_epoch(lesson.iter().cloned(), 1, 0.);
+		assert_eq!(with_penalty, loss + regularizer.penalty(&reported.weights));
+
+		// the penalty of every layer counts
+		let mut network = InnerLayer::<Sigmoid, U3, U1, U2, _>::push(reported.clone())
+			.with_regularizer(regularizer.with_reported_penalty());
+		let loss = network.evaluate(&arr![f32; 0.5], &arr![f32; 1., 0.]);
+		let with_penalty =
+			network.teach_epoch([(arr![f32; 0.5], arr![f32; 1., 0.])].iter().cloned(), 1, 0.);
+		let penalty =
+			regularizer.penalty(&network.inner.weights) + regularizer.penalty(&reported.weights);
+		assert!((with_penalty - loss - penalty).abs() < 1e-6);
+	}
+}
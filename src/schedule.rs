@@ -0,0 +1,205 @@
+//! Learning rate schedules decide how fast a network learns at each point of its training.
+//!
+//! Usually training starts with big steps to quickly get close to a good solution and then slows
+//! down to fine-tune it.
+use core::f32::consts::PI;
+
+pub trait LearningRateSchedule {
+	/// the speed for the given epoch (counting from 0) out of epochs
+	fn speed(&self, epoch: usize, epochs: usize) -> f32;
+
+	/// gets called with the average loss at the end of every epoch
+	fn end_epoch(&mut self, _loss: f32) {}
+}
+
+/// Always the same speed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Constant(pub f32);
+
+impl LearningRateSchedule for Constant {
+	fn speed(&self, _epoch: usize, _epochs: usize) -> f32 { self.0 }
+}
+
+/// Moves from start to end in a straight line over the course of the training.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Linear {
+	pub start: f32,
+	pub end: f32,
+}
+
+impl LearningRateSchedule for Linear {
+	fn speed(&self, epoch: usize, epochs: usize) -> f32 {
+		let percentage = epoch as f32 / epochs as f32;
+		self.start * (1. - percentage) + self.end * percentage
+	}
+}
+
+/// Multiplies the speed by factor every step_size epochs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepDecay {
+	pub initial: f32,
+	pub factor: f32,
+	pub step_size: usize,
+}
+
+impl LearningRateSchedule for StepDecay {
+	fn speed(&self, epoch: usize, _epochs: usize) -> f32 {
+		let steps = epoch / self.step_size.max(1);
+		self.initial * self.factor.powi(steps as i32)
+	}
+}
+
+/// Multiplies the speed by decay every epoch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExponentialDecay {
+	pub initial: f32,
+	pub decay: f32,
+}
+
+impl LearningRateSchedule for ExponentialDecay {
+	fn speed(&self, epoch: usize, _epochs: usize) -> f32 {
+		self.initial * self.decay.powi(epoch as i32)
+	}
+}
+
+/// Follows half a cosine wave from max down to min over the course of the training.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CosineAnnealing {
+	pub max: f32,
+	pub min: f32,
+}
+
+impl LearningRateSchedule for CosineAnnealing {
+	fn speed(&self, epoch: usize, epochs: usize) -> f32 {
+		let percentage = epoch as f32 / epochs as f32;
+		self.min + 0.5 * (self.max - self.min) * (1. + (PI * percentage).cos())
+	}
+}
+
+/// Ramps the speed up from almost 0 over the first warmup epochs, then follows the wrapped
+/// schedule.
+///
+/// Big steps on a freshly initialized network can throw it off, especially with adaptive
+/// [optimizers](crate::optimizer).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinearWarmup<S: LearningRateSchedule> {
+	pub warmup: usize,
+	pub schedule: S,
+}
+
+impl<S: LearningRateSchedule> LearningRateSchedule for LinearWarmup<S> {
+	fn speed(&self, epoch: usize, epochs: usize) -> f32 {
+		let speed = self.schedule.speed(epoch, epochs);
+		if epoch < self.warmup {
+			speed * (epoch + 1) as f32 / self.warmup as f32
+		} else {
+			speed
+		}
+	}
+
+	fn end_epoch(&mut self, loss: f32) { self.schedule.end_epoch(loss) }
+}
+
+/// Keeps the speed until the loss stops improving for patience epochs, then multiplies it by
+/// factor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReduceOnPlateau {
+	speed: f32,
+	factor: f32,
+	patience: usize,
+	min_speed: f32,
+	best: f32,
+	bad_epochs: usize,
+}
+
+impl ReduceOnPlateau {
+	pub fn new(speed: f32, factor: f32, patience: usize) -> Self {
+		Self {
+			speed,
+			factor,
+			patience,
+			min_speed: 0.,
+			best: f32::INFINITY,
+			bad_epochs: 0,
+		}
+	}
+
+	/// never reduce the speed below min_speed
+	pub fn with_min_speed(mut self, min_speed: f32) -> Self {
+		self.min_speed = min_speed;
+		self
+	}
+}
+
+impl LearningRateSchedule for ReduceOnPlateau {
+	fn speed(&self, _epoch: usize, _epochs: usize) -> f32 { self.speed }
+
+	fn end_epoch(&mut self, loss: f32) {
+		if loss < self.best {
+			self.best = loss;
+			self.bad_epochs = 0;
+		} else {
+			self.bad_epochs += 1;
+			if self.bad_epochs > self.patience {
+				self.speed = (self.speed * self.factor).max(self.min_speed);
+				self.bad_epochs = 0;
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn close(a: f32, b: f32) -> bool { (a - b).abs() < 1e-6 }
+
+	#[test]
+	fn decays() {
+		let step = StepDecay {
+			initial: 1.,
+			factor: 0.5,
+			step_size: 10,
+		};
+		assert!(close(step.speed(9, 100), 1.));
+		assert!(close(step.speed(10, 100), 0.5));
+		assert!(close(step.speed(25, 100), 0.25));
+
+		let exponential = ExponentialDecay {
+			initial: 2.,
+			decay: 0.5,
+		};
+		assert!(close(exponential.speed(3, 100), 0.25));
+
+		let cosine = CosineAnnealing { max: 1., min: 0.1 };
+		assert!(close(cosine.speed(0, 100), 1.));
+		assert!(close(cosine.speed(50, 100), 0.55));
+		assert!(cosine.speed(99, 100) < 0.11);
+	}
+
+	#[test]
+	fn warmup() {
+		let warmup = LinearWarmup {
+			warmup: 4,
+			schedule: Constant(1.),
+		};
+		assert!(close(warmup.speed(0, 100), 0.25));
+		assert!(close(warmup.speed(3, 100), 1.));
+		assert!(close(warmup.speed(50, 100), 1.));
+	}
+
+	#[test]
+	fn plateau() {
+		let mut plateau = ReduceOnPlateau::new(1., 0.5, 1).with_min_speed(0.3);
+		for loss in &[3., 2., 2., 2.] {
+			plateau.end_epoch(*loss);
+		}
+		assert!(close(plateau.speed(0, 0), 0.5));
+		plateau.end_epoch(1.);
+		assert!(close(plateau.speed(0, 0), 0.5));
+		for _ in 0..4 {
+			plateau.end_epoch(1.);
+		}
+		assert!(close(plateau.speed(0, 0), 0.3));
+	}
+}
@@ -0,0 +1,50 @@
+//! helpers shared by the tests of the layers
+use crate::layers::{Differentiable, Network, AL};
+use generic_array::GenericArray;
+use std::vec::Vec;
+
+/// reproducible values between -0.5 and 0.5, different for every seed
+pub fn sample<L: AL>(seed: usize) -> GenericArray<f32, L> {
+	(0..L::to_usize())
+		.map(|i| ((i * 7 + seed) % 11) as f32 / 10. - 0.5)
+		.collect()
+}
+
+/// every parameter of network with its delta, in the order of
+/// [_params_mut](Differentiable::_params_mut)
+pub fn params<N: Differentiable<I, O>, I: AL, O: AL>(network: &mut N) -> Vec<(f32, f32)> {
+	let mut params = Vec::new();
+	network._params_mut(&mut |param, delta| params.push((*param, *delta)));
+	params
+}
+
+/// asserts that accumulating the examples as one batch gives the same errors of the inputs, loss
+/// and deltas as accumulating them one by one
+pub fn assert_batch_matches<N, I: AL, O: AL, const B: usize>(
+	network: &N,
+	inputs: &[GenericArray<f32, I>; B],
+	outputs: &[GenericArray<f32, O>; B],
+) where
+	N: Differentiable<I, O> + Clone,
+{
+	let mut single = network.clone();
+	let mut loss = 0.;
+	let mut errors = Vec::new();
+	for (input, output) in inputs.iter().zip(outputs) {
+		let (error, example_loss) = single.accumulate(input, output);
+		loss += example_loss / B as f32;
+		errors.push(error);
+	}
+
+	let mut batch = network.clone();
+	let (batch_errors, batch_loss) = batch.accumulate_batch(inputs, outputs);
+	assert!((batch_loss - loss).abs() < 1e-5, "{} {}", batch_loss, loss);
+	for (batch_error, error) in batch_errors.iter().zip(&errors) {
+		for (b, e) in batch_error.iter().zip(error) {
+			assert!((b - e).abs() < 1e-5, "{:?} {:?}", batch_error, error);
+		}
+	}
+	for (b, s) in params(&mut batch).into_iter().zip(params(&mut single)) {
+		assert!((b.1 - s.1).abs() < 1e-5, "{:?} {:?}", b, s);
+	}
+}
@@ -0,0 +1,220 @@
+//! Training that keeps an eye on how well the network does on examples it is not trained on.
+//!
+//! [teach](crate::layers::Network::teach) runs for a fixed number of iterations, no matter if the
+//! network is still improving or has started to learn its lesson by heart. A [Trainer] checks the
+//! loss on a separate validation set after every epoch, stops once it does not get any better and
+//! hands back the network from its best epoch.
+use crate::{
+	layers::{Network, AL},
+	schedule::LearningRateSchedule,
+};
+use generic_array::GenericArray;
+
+/// Trains a network, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Trainer<Net, S: LearningRateSchedule> {
+	network: Net,
+	schedule: S,
+	epochs: usize,
+	batch_size: usize,
+	patience: Option<usize>,
+}
+
+/// What happened in an epoch, passed to the checkpoint hook of [fit](Trainer::fit).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Epoch {
+	pub epoch: usize,
+	pub speed: f32,
+	/// average loss on the training examples, measured while training on them
+	pub training_loss: f32,
+	/// average loss on the validation examples after the epoch
+	pub validation_loss: f32,
+	/// whether this is the best validation loss so far
+	pub improved: bool,
+}
+
+/// How a call to [fit](Trainer::fit) went.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+	/// number of epochs that were run
+	pub epochs: usize,
+	/// the epoch the network was taken from
+	pub best_epoch: usize,
+	pub best_validation_loss: f32,
+	/// whether training stopped before running all epochs
+	pub stopped_early: bool,
+}
+
+impl<Net: Clone, S: LearningRateSchedule> Trainer<Net, S> {
+	/// trains for 100 epochs with a batch size of 1 and no early stopping by default
+	pub fn new(network: Net, schedule: S) -> Self {
+		Self {
+			network,
+			schedule,
+			epochs: 100,
+			batch_size: 1,
+			patience: None,
+		}
+	}
+
+	/// the maximum number of epochs to train for
+	pub fn with_epochs(mut self, epochs: usize) -> Self {
+		self.epochs = epochs;
+		self
+	}
+
+	/// how many examples to average over for each correction of the weights
+	pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+		self.batch_size = batch_size;
+		self
+	}
+
+	/// stop once the validation loss has not improved for patience epochs
+	pub fn with_patience(mut self, patience: usize) -> Self {
+		self.patience = Some(patience);
+		self
+	}
+
+	pub fn network(&self) -> &Net { &self.network }
+
+	pub fn into_network(self) -> Net { self.network }
+
+	/// Trains on the training examples and checks the loss on the validation examples after every
+	/// epoch.
+	///
+	/// checkpoint gets called at the end of every epoch with the current network, for example to
+	/// serialize it.
+	///
+	/// Afterwards the network is reset to the one with the lowest validation loss.
+	pub fn fit<Input, FinalOut, T, V, C>(
+		&mut self,
+		training: T,
+		validation: V,
+		mut checkpoint: C,
+	) -> Report
+	where
+		Input: AL,
+		FinalOut: AL,
+		Net: Network<Input, FinalOut>,
+		T: IntoIterator<Item = (GenericArray<f32, Input>, GenericArray<f32, FinalOut>)>,
+		T::IntoIter: Clone,
+		V: IntoIterator<Item = (GenericArray<f32, Input>, GenericArray<f32, FinalOut>)>,
+		V::IntoIter: Clone,
+		C: FnMut(&Epoch, &Net),
+	{
+		let training = training.into_iter();
+		let validation = validation.into_iter();
+
+		let mut best = None;
+		let mut report = Report {
+			epochs: 0,
+			best_epoch: 0,
+			best_validation_loss: f32::INFINITY,
+			stopped_early: false,
+		};
+		let mut epochs_without_improvement = 0;
+
+		for epoch in 0..self.epochs {
+			let speed = self.schedule.speed(epoch, self.epochs);
+			let training_loss = self
+				.network
+				.teach_epoch(training.clone(), self.batch_size, speed);
+
+			let mut validation_loss = 0f32;
+			for (pos, (input, output)) in validation.clone().enumerate() {
+				let loss = self.network.evaluate(&input, &output);
+				validation_loss += (loss - validation_loss) / ((pos + 1) as f32);
+			}
+			self.schedule.end_epoch(validation_loss);
+
+			let improved = validation_loss < report.best_validation_loss;
+			if improved {
+				report.best_epoch = epoch;
+				report.best_validation_loss = validation_loss;
+				best = Some(self.network.clone());
+				epochs_without_improvement = 0;
+			} else {
+				epochs_without_improvement += 1;
+			}
+			report.epochs = epoch + 1;
+
+			let info = Epoch {
+				epoch,
+				speed,
+				training_loss,
+				validation_loss,
+				improved,
+			};
+			checkpoint(&info, &self.network);
+
+			if let Some(patience) = self.patience {
+				if epochs_without_improvement >= patience {
+					report.stopped_early = report.epochs < self.epochs;
+					break;
+				}
+			}
+		}
+
+		if let Some(best) = best {
+			self.network = best;
+		}
+		report
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{activation::Sigmoid, layers::OutputLayer, schedule::Constant};
+	use generic_array::{
+		arr,
+		typenum::{U1, U2},
+	};
+
+	fn or() -> impl Iterator<Item = (GenericArray<f32, U2>, GenericArray<f32, U1>)> + Clone {
+		[(0., 0., 0.), (0., 1., 1.), (1., 0., 1.), (1., 1., 1.)]
+			.iter()
+			.map(|&(a, b, out)| (arr![f32; a, b], arr![f32; out]))
+	}
+
+	#[test]
+	fn learns_and_checkpoints() {
+		let mut trainer = Trainer::new(OutputLayer::<Sigmoid, U1, U2>::new(), Constant(0.9))
+			.with_epochs(200)
+			.with_patience(5);
+		let mut checkpoints = 0;
+		let report = trainer.fit(or(), or(), |epoch, _| {
+			assert_eq!(epoch.epoch, checkpoints);
+			checkpoints += 1;
+		});
+		assert_eq!(report.epochs, checkpoints);
+		assert!(report.best_validation_loss < 0.05);
+
+		let network = trainer.into_network();
+		for (input, output) in or() {
+			assert!((network.calculate(&input)[0] - output[0]).abs() < 0.3);
+		}
+	}
+
+	#[test]
+	fn stops_early_and_keeps_best() {
+		// validating against the opposite of what is trained only gets worse over time
+		let not_or = or().map(|(input, output)| (input, arr![f32; 1. - output[0]]));
+		let mut trainer = Trainer::new(OutputLayer::<Sigmoid, U1, U2>::new(), Constant(0.9))
+			.with_epochs(1000)
+			.with_patience(3);
+		let mut losses = std::vec::Vec::new();
+		let report = trainer.fit(or(), not_or.clone(), |epoch, _| {
+			losses.push(epoch.validation_loss)
+		});
+
+		assert!(report.stopped_early);
+		assert_eq!(report.epochs, report.best_epoch + 4);
+		let best = losses.iter().cloned().fold(f32::INFINITY, f32::min);
+		assert_eq!(report.best_validation_loss, best);
+
+		let network = trainer.into_network();
+		let loss = not_or.map(|(i, o)| network.evaluate(&i, &o)).sum::<f32>() / 4.;
+		assert!((loss - best).abs() < 1e-6);
+	}
+}
@@ -0,0 +1,668 @@
+//! Layers growing each plane of a 3d input, for decoders that have to restore images from a
+//! smaller code, like the decoder of an [Autoencoder](crate::autoencoder::Autoencoder).
+//!
+//! [ConvTranspose2d] learns how to spread each input over a larger area, [Upsample2d] just
+//! interpolates between its inputs and has nothing to learn. Both use the same layout as a
+//! [ConvolutionalLayer](crate::convolution::ConvolutionalLayer), and the sizes of their outputs
+//! are known at compile time just like [ConvOutputSize](crate::convolution::ConvOutputSize).
+//!
+//! for generic layer documentation see [layers](crate::layers)
+use core::{
+	fmt::Debug,
+	marker::PhantomData,
+	ops::{Add, Mul, Sub},
+};
+use generic_array::{
+	typenum::{bit::B1, Diff, Prod, Sub1, Sum, Unsigned, U2},
+	GenericArray,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+	convolution::{Connections, Convolution, DilatedSize, Padding, PaddingFor},
+	layers::{sealed, Differentiable, AL, NL},
+	optimizer::Sgd,
+};
+
+pub type ConvTransposeOutputSize<W, H, CW, CH, N, SH, SW, P, DL> = Prod<
+	Prod<
+		ConvTransposeHSize<H, CH, SH, <P as Padding>::Height, DL>,
+		ConvTransposeWSize<W, CW, SW, <P as Padding>::Width, DL>,
+	>,
+	N,
+>;
+/// the height of each plane of the output, the inverse of
+/// [ConvHSize](crate::convolution::ConvHSize)
+pub type ConvTransposeHSize<H, CH, SH, PH, DL> =
+	Diff<Sum<Prod<Sub1<H>, SH>, DilatedSize<CH, DL>>, Prod<PH, U2>>;
+/// the width of each plane of the output, the inverse of
+/// [ConvWSize](crate::convolution::ConvWSize)
+pub type ConvTransposeWSize<W, CW, SW, PW, DL> =
+	Diff<Sum<Prod<Sub1<W>, SW>, DilatedSize<CW, DL>>, Prod<PW, U2>>;
+
+pub type UpsampleOutputSize<W, H, D, FW, FH> =
+	Prod<Prod<UpsampleHSize<H, FH>, UpsampleWSize<W, FW>>, D>;
+/// the height of each plane of the output
+pub type UpsampleHSize<H, FH> = Prod<H, FH>;
+/// the width of each plane of the output
+pub type UpsampleWSize<W, FW> = Prod<W, FW>;
+
+/// Spreads each input over a CH * CW area of the output, weighted by N filters spanning the full
+/// depth D of the input. Inputs SH rows and SW columns apart land that far apart in the output,
+/// where their areas overlap they get summed up. The weights of the filter are spread DL - 1
+/// positions apart.
+///
+/// This is the backward pass of a [ConvolutionalLayer](crate::convolution::ConvolutionalLayer)
+/// with the same filters, strides, padding and dilation, so it restores the size that convolution
+/// shrank its input to. The padding gets cut off the edges of the output again, for
+/// [Reflect](crate::convolution::Reflect) what lands on it is folded back into the output.
+pub type ConvTranspose2d<A, W, H, D, CW, CH, N, SH, SW, P, DL, FinalOut, Next, O = Sgd> =
+	Convolution<A, Transposed<W, H, D, CW, CH, N, SH, SW, P, DL>, FinalOut, Next, O>;
+
+/// The connections of a [ConvTranspose2d], see there for the meaning of the parameters.
+#[derive(Debug, Clone)]
+pub struct Transposed<W, H, D, CW, CH, N, SH, SW, P, DL>(
+	PhantomData<(W, H, D, CW, CH, N, SH, SW, P, DL)>,
+);
+
+impl<
+		W: AL,
+		H: AL,
+		D: AL,
+		CW: AL,
+		CH: AL,
+		N: NL<Prod<Prod<CH, CW>, D>>,
+		SH: AL,
+		SW: AL,
+		P: Padding,
+		DL: AL,
+	> Connections for Transposed<W, H, D, CW, CH, N, SH, SW, P, DL>
+where
+	H: Mul<W>,
+	Prod<H, W>: Mul<D>,
+	Prod<Prod<H, W>, D>: AL,
+
+	CH: Mul<CW>,
+	Prod<CH, CW>: Mul<D>,
+	Prod<Prod<CH, CW>, D>: AL,
+
+	CH: Sub<B1>,
+	Sub1<CH>: Mul<DL>,
+	Prod<Sub1<CH>, DL>: Add<B1>,
+	H: Sub<B1>,
+	Sub1<H>: Mul<SH>,
+	Prod<Sub1<H>, SH>: Add<DilatedSize<CH, DL>>,
+	P::Height: Mul<U2>,
+	Sum<Prod<Sub1<H>, SH>, DilatedSize<CH, DL>>: Sub<Prod<P::Height, U2>>,
+
+	CW: Sub<B1>,
+	Sub1<CW>: Mul<DL>,
+	Prod<Sub1<CW>, DL>: Add<B1>,
+	W: Sub<B1>,
+	Sub1<W>: Mul<SW>,
+	Prod<Sub1<W>, SW>: Add<DilatedSize<CW, DL>>,
+	P::Width: Mul<U2>,
+	Sum<Prod<Sub1<W>, SW>, DilatedSize<CW, DL>>: Sub<Prod<P::Width, U2>>,
+
+	ConvTransposeHSize<H, CH, SH, P::Height, DL>: Mul<ConvTransposeWSize<W, CW, SW, P::Width, DL>>,
+	ConvTransposeHSize<H, CH, SH, P::Height, DL>: AL,
+	ConvTransposeWSize<W, CW, SW, P::Width, DL>: AL,
+	Prod<ConvTransposeHSize<H, CH, SH, P::Height, DL>, ConvTransposeWSize<W, CW, SW, P::Width, DL>>:
+		Mul<N>,
+
+	ConvTransposeOutputSize<W, H, CW, CH, N, SH, SW, P, DL>: AL,
+
+	// the padding is cut off the output, so it has to fit that
+	P: PaddingFor<
+		ConvTransposeHSize<H, CH, SH, <P as Padding>::Height, DL>,
+		ConvTransposeWSize<W, CW, SW, <P as Padding>::Width, DL>,
+	>,
+{
+	type Input = Prod<Prod<H, W>, D>;
+	type Output = ConvTransposeOutputSize<W, H, CW, CH, N, SH, SW, P, DL>;
+	type Weights = Prod<Prod<CH, CW>, D>;
+	type Filters = N;
+
+	#[inline(always)]
+	fn for_each_connection<F: FnMut(usize, usize, usize, usize)>(mut f: F) {
+		let width = W::to_usize();
+		let height = H::to_usize();
+		let filter_width = CW::to_usize();
+		let filter_height = CH::to_usize();
+		let stride_height = SH::to_usize();
+		let stride_width = SW::to_usize();
+		let padding_height = P::Height::to_usize() as isize;
+		let padding_width = P::Width::to_usize() as isize;
+		let dilation = DL::to_usize();
+		let output_height = ConvTransposeHSize::<H, CH, SH, P::Height, DL>::to_usize();
+		let output_width = ConvTransposeWSize::<W, CW, SW, P::Width, DL>::to_usize();
+
+		// the loops of a convolution turned inside out, every input visits its area of the output
+		for filter in 0..N::to_usize() {
+			for d in 0..D::to_usize() {
+				for y in 0..height {
+					for x in 0..width {
+						let input = (d * height + y) * width + x;
+						for filter_y in 0..filter_height {
+							let padded_y = (y * stride_height + filter_y * dilation) as isize;
+							// weights over the cut off padding have nothing to connect to
+							let out_y = match P::source(padded_y - padding_height, output_height) {
+								Some(out_y) => out_y,
+								None => continue,
+							};
+							for filter_x in 0..filter_width {
+								let padded_x = (x * stride_width + filter_x * dilation) as isize;
+								let out_x = match P::source(padded_x - padding_width, output_width)
+								{
+									Some(out_x) => out_x,
+									None => continue,
+								};
+								let output =
+									(filter * output_height + out_y) * output_width + out_x;
+								let weight =
+									(d * filter_height + filter_y) * filter_width + filter_x;
+								f(output, filter, weight, input);
+							}
+						}
+					}
+				}
+			}
+		}
+	}
+}
+
+/// How an [Upsample2d] fills the positions between its inputs.
+pub trait Interpolation: Debug + Clone + Serialize + DeserializeOwned {
+	/// calls `f(input, weight)` for the inputs that make up position of an output dimension that
+	/// is factor times as large as the input dimension of the given size
+	fn sources<F: FnMut(usize, f32)>(position: usize, size: usize, factor: usize, f: F);
+}
+
+/// Repeats every input factor times.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Nearest {}
+
+impl Interpolation for Nearest {
+	fn sources<F: FnMut(usize, f32)>(position: usize, _size: usize, factor: usize, mut f: F) {
+		f(position / factor, 1.)
+	}
+}
+
+/// Blends the two closest inputs, weighted by how close each of them is. Outputs beyond the
+/// centers of the outermost inputs repeat them.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Bilinear {}
+
+impl Interpolation for Bilinear {
+	fn sources<F: FnMut(usize, f32)>(position: usize, size: usize, factor: usize, mut f: F) {
+		// the center of the output in the coordinates of the inputs
+		let source = ((position as f32 + 0.5) / factor as f32 - 0.5).max(0.);
+		let before = source as usize;
+		if before + 1 >= size {
+			f(size - 1, 1.);
+		} else {
+			let after = source - before as f32;
+			f(before, 1. - after);
+			f(before + 1, after);
+		}
+	}
+}
+
+/// Scales each of the D planes of its input up by FH rows and FW columns per input, using the
+/// [Interpolation] M.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "W: AL, H: AL, D: AL, FW: AL, FH: AL, FinalOut: AL")]
+pub struct Upsample2d<
+	// width of the input
+	W: AL,
+	// height of the input
+	H: AL,
+	// depth of the input
+	D: AL,
+	// horizontal scale factor
+	FW: AL,
+	// vertical scale factor
+	FH: AL,
+	M: Interpolation,
+	FinalOut: AL,
+	Next: Differentiable<UpsampleOutputSize<W, H, D, FW, FH>, FinalOut>,
+> where
+	H: Mul<W>,
+	Prod<H, W>: Mul<D>,
+	Prod<Prod<H, W>, D>: AL,
+
+	H: Mul<FH>,
+	W: Mul<FW>,
+	UpsampleHSize<H, FH>: Mul<UpsampleWSize<W, FW>>,
+	Prod<UpsampleHSize<H, FH>, UpsampleWSize<W, FW>>: Mul<D>,
+
+	UpsampleOutputSize<W, H, D, FW, FH>: AL,
+{
+	phantom: core::marker::PhantomData<(W, H, D, FW, FH, M, FinalOut)>,
+	next: Next,
+}
+
+impl<
+		W: AL,
+		H: AL,
+		D: AL,
+		FW: AL,
+		FH: AL,
+		M: Interpolation,
+		FinalOut: AL,
+		Next: Differentiable<UpsampleOutputSize<W, H, D, FW, FH>, FinalOut>,
+	> Upsample2d<W, H, D, FW, FH, M, FinalOut, Next>
+where
+	H: Mul<W>,
+	Prod<H, W>: Mul<D>,
+	Prod<Prod<H, W>, D>: AL,
+
+	H: Mul<FH>,
+	W: Mul<FW>,
+	UpsampleHSize<H, FH>: Mul<UpsampleWSize<W, FW>>,
+	Prod<UpsampleHSize<H, FH>, UpsampleWSize<W, FW>>: Mul<D>,
+
+	UpsampleOutputSize<W, H, D, FW, FH>: AL,
+{
+	/// Pushes this layer on top of an existing layer.
+	pub fn push(next: Next) -> Self {
+		Self {
+			phantom: core::marker::PhantomData,
+			next,
+		}
+	}
+
+	/// calls `f(output, input, weight)` for every input each output gets interpolated from
+	#[inline(always)]
+	fn for_each_source<F: FnMut(usize, usize, f32)>(mut f: F) {
+		let width = W::to_usize();
+		let height = H::to_usize();
+		let factor_width = FW::to_usize();
+		let factor_height = FH::to_usize();
+		let output_height = height * factor_height;
+		let output_width = width * factor_width;
+
+		for d in 0..D::to_usize() {
+			for out_y in 0..output_height {
+				for out_x in 0..output_width {
+					let output = (d * output_height + out_y) * output_width + out_x;
+					M::sources(out_y, height, factor_height, |y, weight_y| {
+						M::sources(out_x, width, factor_width, |x, weight_x| {
+							f(output, (d * height + y) * width + x, weight_y * weight_x)
+						})
+					});
+				}
+			}
+		}
+	}
+
+	fn upsample(
+		inputs: &GenericArray<f32, Prod<Prod<H, W>, D>>,
+	) -> GenericArray<f32, UpsampleOutputSize<W, H, D, FW, FH>> {
+		let mut outputs = GenericArray::<f32, UpsampleOutputSize<W, H, D, FW, FH>>::default();
+		Self::for_each_source(|output, input, weight| outputs[output] += weight * inputs[input]);
+		outputs
+	}
+}
+
+impl<
+		W: AL,
+		H: AL,
+		D: AL,
+		FW: AL,
+		FH: AL,
+		M: Interpolation,
+		FinalOut: AL,
+		Next: Differentiable<UpsampleOutputSize<W, H, D, FW, FH>, FinalOut>,
+	> sealed::Sealed for Upsample2d<W, H, D, FW, FH, M, FinalOut, Next>
+where
+	H: Mul<W>,
+	Prod<H, W>: Mul<D>,
+	Prod<Prod<H, W>, D>: AL,
+
+	H: Mul<FH>,
+	W: Mul<FW>,
+	UpsampleHSize<H, FH>: Mul<UpsampleWSize<W, FW>>,
+	Prod<UpsampleHSize<H, FH>, UpsampleWSize<W, FW>>: Mul<D>,
+
+	UpsampleOutputSize<W, H, D, FW, FH>: AL,
+{
+}
+
+impl<
+		W: AL,
+		H: AL,
+		D: AL,
+		FW: AL,
+		FH: AL,
+		M: Interpolation,
+		FinalOut: AL,
+		Next: Differentiable<UpsampleOutputSize<W, H, D, FW, FH>, FinalOut>,
+	> Differentiable<Prod<Prod<H, W>, D>, FinalOut> for Upsample2d<W, H, D, FW, FH, M, FinalOut, Next>
+where
+	H: Mul<W>,
+	Prod<H, W>: Mul<D>,
+	Prod<Prod<H, W>, D>: AL,
+
+	H: Mul<FH>,
+	W: Mul<FW>,
+	UpsampleHSize<H, FH>: Mul<UpsampleWSize<W, FW>>,
+	Prod<UpsampleHSize<H, FH>, UpsampleWSize<W, FW>>: Mul<D>,
+
+	UpsampleOutputSize<W, H, D, FW, FH>: AL,
+{
+	fn _calculate(
+		&self,
+		inputs: &GenericArray<f32, Prod<Prod<H, W>, D>>,
+	) -> GenericArray<f32, FinalOut> {
+		self.next._calculate(&Self::upsample(inputs))
+	}
+
+	fn _loss(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> f32 {
+		Next::_loss(output, correct_output)
+	}
+
+	fn _error(
+		output: &GenericArray<f32, FinalOut>,
+		correct_output: &GenericArray<f32, FinalOut>,
+	) -> (GenericArray<f32, FinalOut>, f32) {
+		Next::_error(output, correct_output)
+	}
+
+	fn _accumulate_with<F>(
+		&mut self,
+		inputs: &GenericArray<f32, Prod<Prod<H, W>, D>>,
+		objective: F,
+	) -> (GenericArray<f32, Prod<Prod<H, W>, D>>, f32)
+	where
+		F: FnOnce(&GenericArray<f32, FinalOut>) -> (GenericArray<f32, FinalOut>, f32),
+	{
+		let (error, loss) = self
+			.next
+			._accumulate_with(&Self::upsample(inputs), objective);
+		// every input gets the error of the outputs it was blended into, weighted the same way
+		let mut pre_error = GenericArray::<f32, Prod<Prod<H, W>, D>>::default();
+		Self::for_each_source(|output, input, weight| pre_error[input] += weight * error[output]);
+		(pre_error, loss)
+	}
+
+	fn _accumulate_batch<const B: usize, F>(
+		&mut self,
+		inputs: &[GenericArray<f32, Prod<Prod<H, W>, D>>; B],
+		objective: F,
+	) -> ([GenericArray<f32, Prod<Prod<H, W>, D>>; B], f32)
+	where
+		F: FnOnce(&[GenericArray<f32, FinalOut>; B]) -> ([GenericArray<f32, FinalOut>; B], f32),
+	{
+		let outputs = inputs.each_ref().map(Self::upsample);
+		let (errors, loss) = self.next._accumulate_batch(&outputs, objective);
+		let pre_errors = errors.map(|error| {
+			let mut pre_error = GenericArray::<f32, Prod<Prod<H, W>, D>>::default();
+			Self::for_each_source(|output, input, weight| {
+				pre_error[input] += weight * error[output]
+			});
+			pre_error
+		});
+		(pre_errors, loss)
+	}
+
+	fn _apply_deltas(&mut self, batch_size: usize, speed: f32) {
+		self.next._apply_deltas(batch_size, speed);
+	}
+
+	fn _params_mut<F: FnMut(&mut f32, &mut f32)>(&mut self, f: &mut F) { self.next._params_mut(f); }
+
+	fn _penalty(&self) -> f32 { self.next._penalty() }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		activation::{Identity, Sigmoid},
+		autoencoder::{Autoencoder, Regularization},
+		convolution::{ConvHSize, Full, Reflect, Valid, Zeros},
+		gradcheck::check,
+		layers::OutputLayer,
+		loss::Mse,
+		optimizer::Adam,
+		schedule::Constant,
+		testing::{assert_batch_matches, sample},
+	};
+	use generic_array::{
+		arr,
+		typenum::{U0, U1, U12, U16, U2, U3, U4, U5, U8},
+	};
+	use std::vec::Vec;
+
+	#[test]
+	fn output_size() {
+		assert_eq!(ConvTransposeHSize::<U2, U2, U2, U0, U1>::to_usize(), 4);
+		assert_eq!(ConvTransposeWSize::<U3, U2, U1, U0, U1>::to_usize(), 4);
+		assert_eq!(
+			ConvTransposeOutputSize::<U3, U2, U3, U2, U2, U2, U2, Valid, U1>::to_usize(),
+			4 * 7 * 2
+		);
+		// restores the 5 rows a padded and dilated convolution shrinks to 3
+		assert_eq!(ConvHSize::<U5, U3, U1, U1, U2>::to_usize(), 3);
+		assert_eq!(ConvTransposeHSize::<U3, U3, U1, U1, U2>::to_usize(), 5);
+		assert_eq!(
+			UpsampleOutputSize::<U3, U2, U2, U2, U3>::to_usize(),
+			6 * 6 * 2
+		);
+	}
+
+	#[test]
+	fn spreads_inputs_over_the_filter() {
+		// apart each input gets its own copy of the filter, overlapping they add up
+		let mut apart =
+			ConvTranspose2d::<Identity, U2, U1, U1, U2, U1, U1, U1, U2, Valid, U1, U1, _>::push(
+				OutputLayer::<Identity, U1, U4>::new(),
+			);
+		let mut overlapping =
+			ConvTranspose2d::<Identity, U2, U1, U1, U2, U1, U1, U1, U1, Valid, U1, U1, _>::push(
+				OutputLayer::<Identity, U1, U3>::new(),
+			);
+		let filter = [1., 2.];
+		let set = |index: &mut usize, param: &mut f32| {
+			*param = filter.get(*index).cloned().unwrap_or(0.);
+			*index += 1;
+		};
+		let mut index = 0;
+		apart._params_mut(&mut |param, _| set(&mut index, param));
+		let mut index = 0;
+		overlapping._params_mut(&mut |param, _| set(&mut index, param));
+
+		let input = arr![f32; 3., 5.];
+		assert_eq!(
+			apart.step(&apart.weight(&input)),
+			arr![f32; 3., 6., 5., 10.]
+		);
+		assert_eq!(
+			overlapping.step(&overlapping.weight(&input)),
+			arr![f32; 3., 11., 10.]
+		);
+	}
+
+	/// the connections of C as `(output, input, filter, channel, position in the filter)` of a
+	/// transposed convolution, with K positions in each channel of a filter
+	fn connections<C: Connections>(transposed: bool, k: usize) -> Vec<[usize; 5]> {
+		let mut connections = Vec::new();
+		C::for_each_connection(|output, filter, weight, input| {
+			connections.push(if transposed {
+				[output, input, filter, weight / k, weight % k]
+			} else {
+				// the outputs of the convolution are the inputs of the transposed convolution
+				[input, output, weight / k, filter, weight % k]
+			})
+		});
+		connections.sort();
+		connections
+	}
+
+	#[test]
+	fn is_the_transpose_of_a_convolution() {
+		type Transpose<P> = Transposed<U3, U3, U2, U2, U2, U1, U2, U2, P, U2>;
+		type Conv<P> = Full<U5, U5, U1, U2, U2, U2, U2, U2, P, U2>;
+		assert_eq!(
+			connections::<Transpose<Zeros<U1, U1>>>(true, 4),
+			connections::<Conv<Zeros<U1, U1>>>(false, 4)
+		);
+		assert_eq!(
+			connections::<Transpose<Reflect<U1, U1>>>(true, 4),
+			connections::<Conv<Reflect<U1, U1>>>(false, 4)
+		);
+	}
+
+	#[test]
+	fn interpolates() {
+		type Up<M> = Upsample2d<U2, U1, U1, U2, U2, M, U1, OutputLayer<Sigmoid, U1, U8>>;
+		let input = arr![f32; 0., 1.];
+		assert_eq!(
+			Up::<Nearest>::upsample(&input),
+			arr![f32; 0., 0., 1., 1., 0., 0., 1., 1.]
+		);
+		assert_eq!(
+			Up::<Bilinear>::upsample(&input),
+			arr![f32; 0., 0.25, 0.75, 1., 0., 0.25, 0.75, 1.]
+		);
+	}
+
+	#[test]
+	fn gradients() {
+		// padded on the sides, with a dilated filter
+		type Out = ConvTransposeOutputSize<U3, U2, U2, U2, U2, U2, U1, Zeros<U0, U1>, U2>;
+		let transpose = ConvTranspose2d::<
+			Sigmoid,
+			U3,
+			U2,
+			U2,
+			U2,
+			U2,
+			U2,
+			U2,
+			U1,
+			Zeros<U0, U1>,
+			U2,
+			U2,
+			_,
+		>::push(OutputLayer::<Sigmoid, U2, Out>::new());
+		let result = check(&transpose, &sample::<U12>(1), &sample(2), 1e-2, 1e-2);
+		assert!(result.is_ok(), "{:?}", result);
+
+		let nearest = Upsample2d::<U3, U2, U2, U2, U3, Nearest, U2, _>::push(OutputLayer::<
+			Sigmoid,
+			U2,
+			UpsampleOutputSize<U3, U2, U2, U2, U3>,
+		>::new());
+		let result = check(&nearest, &sample::<U12>(3), &sample(4), 1e-2, 1e-2);
+		assert!(result.is_ok(), "{:?}", result);
+
+		let bilinear = Upsample2d::<U3, U2, U2, U2, U3, Bilinear, U2, _>::push(OutputLayer::<
+			Sigmoid,
+			U2,
+			UpsampleOutputSize<U3, U2, U2, U2, U3>,
+		>::new());
+		let result = check(&bilinear, &sample::<U12>(5), &sample(6), 1e-2, 1e-2);
+		assert!(result.is_ok(), "{:?}", result);
+	}
+
+	#[test]
+	fn batches() {
+		type Out = ConvTransposeOutputSize<U3, U2, U2, U2, U2, U2, U1, Zeros<U0, U1>, U2>;
+		let transpose = ConvTranspose2d::<
+			Sigmoid,
+			U3,
+			U2,
+			U2,
+			U2,
+			U2,
+			U2,
+			U2,
+			U1,
+			Zeros<U0, U1>,
+			U2,
+			U2,
+			_,
+		>::push(OutputLayer::<Sigmoid, U2, Out>::new());
+		let inputs = [sample::<U12>(1), sample(2), sample(3)];
+		let outputs = [sample::<U2>(4), sample(5), sample(6)];
+		assert_batch_matches(&transpose, &inputs, &outputs);
+
+		let nearest = Upsample2d::<U3, U2, U2, U2, U3, Nearest, U2, _>::push(OutputLayer::<
+			Sigmoid,
+			U2,
+			UpsampleOutputSize<U3, U2, U2, U2, U3>,
+		>::new());
+		assert_batch_matches(&nearest, &inputs, &outputs);
+	}
+
+	/// 4x4 images made of four 2x2 blocks, two of them on
+	fn blocks() -> impl Iterator<Item = GenericArray<f32, U16>> + Clone {
+		[0b0011, 0b0101, 0b1001, 0b0110].iter().map(|pattern| {
+			(0..16)
+				.map(|pixel| {
+					let block = pixel / 8 * 2 + pixel % 4 / 2;
+					if pattern >> block & 1 == 1 {
+						0.9
+					} else {
+						0.1
+					}
+				})
+				.collect()
+		})
+	}
+
+	#[test]
+	fn decodes_in_an_autoencoder() {
+		type Encoder = OutputLayer<Sigmoid, U4, U16, Mse, Adam>;
+		type Decoder = ConvTranspose2d<
+			Sigmoid,
+			U2,
+			U2,
+			U1,
+			U2,
+			U2,
+			U1,
+			U2,
+			U2,
+			Valid,
+			U1,
+			U16,
+			OutputLayer<Sigmoid, U16, U16, Mse, Adam>,
+			Adam,
+		>;
+		let mut autoencoder: Autoencoder<U16, U4, Encoder, Decoder> = Autoencoder::new(
+			OutputLayer::new(),
+			ConvTranspose2d::push(OutputLayer::new()),
+		);
+		let before = autoencoder.report(blocks());
+		autoencoder.teach(
+			blocks(),
+			1000,
+			1,
+			Constant(0.01),
+			Regularization::default(),
+			|_, _| {},
+		);
+		let after = autoencoder.report(blocks());
+		assert!(
+			after.max_loss < before.mean_loss / 10.,
+			"{:?} -> {:?}",
+			before,
+			after
+		);
+
+		// an upsampling decoder passes the gradients on to the encoder
+		type Upsampling =
+			Upsample2d<U2, U2, U1, U2, U2, Bilinear, U16, OutputLayer<Sigmoid, U16, U16>>;
+		let autoencoder: Autoencoder<U16, U4, OutputLayer<Sigmoid, U4, U16>, Upsampling> =
+			Autoencoder::new(OutputLayer::new(), Upsample2d::push(OutputLayer::new()));
+		let input = blocks().nth(1).unwrap();
+		let result = check(&autoencoder, &input, &input, 1e-2, 1e-2);
+		assert!(result.is_ok(), "{:?}", result);
+	}
+}